@@ -0,0 +1,41 @@
+/// Everything that can go wrong in the `passman` tool,
+/// on top of what can go wrong with the vault itself
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error(transparent)]
+    Vault(#[from] pass_man::Error),
+    #[error("{kind} entries need the {part}. Give it with {argument}, or use `--interactive`")]
+    MissingArgument {
+        kind: String,
+        part: String,
+        argument: &'static str,
+    },
+    #[error("Clipboard command `{command}` failed")]
+    ClipboardCommandFailed { command: String },
+    #[error(transparent)]
+    ClipboardErr(#[from] arboard::Error),
+    #[error(transparent)]
+    ConfigErr(#[from] confy::ConfyError),
+}
+
+impl AppError {
+    /// A short, stable identifier for the kind of error,
+    /// for use in machine-readable output
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Vault(error) => error.code(),
+            AppError::MissingArgument { .. } => "missing_argument",
+            AppError::ClipboardCommandFailed { .. } => "clipboard_command",
+            AppError::ClipboardErr(_) => "clipboard",
+            AppError::ConfigErr(_) => "config",
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(error: std::io::Error) -> Self {
+        AppError::Vault(error.into())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, AppError>;
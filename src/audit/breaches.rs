@@ -0,0 +1,184 @@
+use std::{cmp::Ordering, fmt::Write, fs::File, path::Path};
+
+use memmap2::Mmap;
+use sha1::{Digest, Sha1};
+use zeroize::Zeroizing;
+
+use crate::error::{Error, Result};
+
+/// The length of a hex-encoded SHA-1 hash
+const HASH_LEN: usize = 40;
+
+/// A local copy of the Pwned Passwords list, in the SHA-1 version ordered by hash,
+/// with one `HASH:COUNT` line per password that has appeared in a data breach.
+///
+/// The file is memory-mapped & binary searched,
+/// so it's never read into memory all at once
+pub struct BreachList {
+    map: Mmap,
+}
+
+impl BreachList {
+    /// Open the list at `path`, checking that it looks like a Pwned Passwords file
+    pub fn open(path: &Path) -> Result<Self> {
+        let invalid = || Error::InvalidBreachList {
+            path: path.to_path_buf(),
+        };
+
+        let file = File::open(path)?;
+        // SAFETY: The map is only ever read, & every line is bounds checked,
+        // so the worst another process changing the file can do is give wrong results
+        let map = unsafe { Mmap::map(&file)? };
+
+        let first = map.split(|&b| b == b'\n').next().unwrap_or_default();
+        let looks_valid = first.len() > HASH_LEN
+            && first[..HASH_LEN].iter().all(u8::is_ascii_hexdigit)
+            && first[HASH_LEN] == b':';
+        if !looks_valid {
+            return Err(invalid());
+        }
+
+        Ok(Self { map })
+    }
+
+    /// How many times `password` has appeared in data breaches,
+    /// or `None` if it isn't in the list
+    pub fn count(&self, password: &[u8]) -> Option<u64> {
+        let mut hash = Zeroizing::new(String::with_capacity(HASH_LEN));
+        for byte in Sha1::digest(password) {
+            write!(hash, "{byte:02X}").expect("writing to a String never fails");
+        }
+
+        let data = &self.map[..];
+        // `lo` & `hi` are always at the start of a line,
+        // with the hash being searched for somewhere between them
+        let (mut lo, mut hi) = (0, data.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let start = data[..mid]
+                .iter()
+                .rposition(|&b| b == b'\n')
+                .map_or(0, |idx| idx + 1);
+            let end = data[mid..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(data.len(), |idx| mid + idx);
+            let line = &data[start..end];
+
+            // Blank lines can only be at the end of the file
+            let Some(line_hash) = line.get(..HASH_LEN) else {
+                hi = start;
+                continue;
+            };
+
+            match compare_hex(line_hash, hash.as_bytes()) {
+                Ordering::Less => lo = end + 1,
+                Ordering::Greater => hi = start,
+                Ordering::Equal => {
+                    let count = std::str::from_utf8(&line[HASH_LEN..]).ok()?;
+                    return count.trim_start_matches(':').trim().parse().ok();
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Compare two hex strings, ignoring case
+fn compare_hex(a: &[u8], b: &[u8]) -> Ordering {
+    a.iter()
+        .map(u8::to_ascii_uppercase)
+        .cmp(b.iter().map(u8::to_ascii_uppercase))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+
+    const PASSWORDS: &[&str] = &[
+        "password", "123456", "hunter2", "qwerty", "letmein", "dragon",
+    ];
+
+    /// A list of `PASSWORDS` ordered by hash like the real one,
+    /// where each password's count is its position in `PASSWORDS` plus one
+    fn write_list(dir: &Path, newline: &str, trailing: &str) -> PathBuf {
+        let mut lines: Vec<String> = PASSWORDS
+            .iter()
+            .zip(1..)
+            .map(|(password, count)| {
+                let hash: String = Sha1::digest(password)
+                    .iter()
+                    .map(|byte| format!("{byte:02X}"))
+                    .collect();
+                format!("{hash}:{count}")
+            })
+            .collect();
+        lines.sort();
+
+        let path = dir.join("pwned-passwords-sha1-ordered-by-hash.txt");
+        fs::write(&path, lines.join(newline) + trailing).unwrap();
+        path
+    }
+
+    fn assert_finds_all(list: &BreachList) {
+        for (password, count) in PASSWORDS.iter().zip(1..) {
+            assert_eq!(list.count(password.as_bytes()), Some(count), "{password}");
+        }
+    }
+
+    #[test]
+    fn finds_first_and_last_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let list = BreachList::open(&write_list(dir.path(), "\n", "")).unwrap();
+
+        // `password` has the lowest hash & `hunter2` the highest
+        assert_eq!(list.count(b"password"), Some(1));
+        assert_eq!(list.count(b"hunter2"), Some(3));
+        assert_finds_all(&list);
+    }
+
+    #[test]
+    fn misses_unbreached_passwords() {
+        let dir = tempfile::tempdir().unwrap();
+        let list = BreachList::open(&write_list(dir.path(), "\n", "\n")).unwrap();
+
+        for i in 0..50 {
+            assert_eq!(list.count(format!("not breached {i}").as_bytes()), None);
+        }
+    }
+
+    #[test]
+    fn handles_crlf_line_endings() {
+        let dir = tempfile::tempdir().unwrap();
+        let list = BreachList::open(&write_list(dir.path(), "\r\n", "\r\n")).unwrap();
+
+        assert_finds_all(&list);
+        assert_eq!(list.count(b"not breached"), None);
+    }
+
+    #[test]
+    fn ignores_trailing_blank_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let list = BreachList::open(&write_list(dir.path(), "\n", "\n\n")).unwrap();
+
+        assert_finds_all(&list);
+        for i in 0..50 {
+            assert_eq!(list.count(format!("not breached {i}").as_bytes()), None);
+        }
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("passwords.txt");
+        fs::write(&path, "password\n123456\n").unwrap();
+
+        assert!(matches!(
+            BreachList::open(&path),
+            Err(Error::InvalidBreachList { .. })
+        ));
+    }
+}
@@ -0,0 +1,249 @@
+mod breaches;
+
+use std::{collections::HashMap, fmt, path::PathBuf};
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    error::Result,
+    manager::{format::timestamp, kinds::EntryKind},
+    Entry,
+};
+pub use breaches::BreachList;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// What [`audit`] looks for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditOptions {
+    /// Passwords that haven't been changed in this many days are reported as old
+    pub max_age_days: u64,
+    /// A local copy of the Pwned Passwords list to check passwords against.
+    /// See [`BreachList`]
+    pub breaches: Option<PathBuf>,
+}
+
+impl Default for AuditOptions {
+    /// Passwords are old after a year, & aren't checked for breaches
+    fn default() -> Self {
+        Self {
+            max_age_days: 365,
+            breaches: None,
+        }
+    }
+}
+
+/// A single problem with an entry
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Finding {
+    /// The password is also used by these entries, given as `<name>:<username>`
+    Reused { with: Vec<String> },
+    /// The password has appeared in data breaches `count` times
+    Breached { count: u64 },
+    /// The password is easy to guess
+    Weak { score: u8, label: &'static str },
+    /// The password hasn't been changed in `days` days
+    Old { days: u64 },
+    /// The entry has no username
+    EmptyUsername,
+    /// These entries, given as `<name>:<username>`, have the same location
+    DuplicateLocation { with: Vec<String> },
+}
+
+impl Finding {
+    /// A short, stable identifier for the kind of problem,
+    /// matching the `kind` it's serialized with
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Finding::Reused { .. } => "reused",
+            Finding::Breached { .. } => "breached",
+            Finding::Weak { .. } => "weak",
+            Finding::Old { .. } => "old",
+            Finding::EmptyUsername => "empty_username",
+            Finding::DuplicateLocation { .. } => "duplicate_location",
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::Reused { with } => write!(f, "Password is also used by {}", names(with)),
+            Finding::Breached { count } => {
+                write!(f, "Password has appeared in data breaches {count} times")
+            }
+            Finding::Weak { label, .. } => write!(f, "Password is {label}"),
+            Finding::Old { days } => write!(f, "Password hasn't been changed in {days} days"),
+            Finding::EmptyUsername => write!(f, "Username is empty"),
+            Finding::DuplicateLocation { with } => {
+                write!(f, "Location is the same as {}", names(with))
+            }
+        }
+    }
+}
+
+/// `a`, `b` & `c`
+fn names(names: &[String]) -> String {
+    let names: Vec<String> = names.iter().map(|name| format!("`{name}`")).collect();
+    match names.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} & {last}", rest.join(", ")),
+        _ => names.concat(),
+    }
+}
+
+/// Everything wrong with a single entry
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntryFindings {
+    /// The entry's `<name>:<username>`, see [`Entry::reference`]
+    pub entry: String,
+    pub id: Uuid,
+    pub findings: Vec<Finding>,
+}
+
+/// How many entries had each kind of problem
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct Summary {
+    pub reused: usize,
+    pub breached: usize,
+    pub weak: usize,
+    pub old: usize,
+    pub empty_username: usize,
+    pub duplicate_location: usize,
+}
+
+/// The results of auditing a vault
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditReport {
+    /// The number of entries audited
+    pub entries: usize,
+    pub summary: Summary,
+    /// The entries with problems, in the order they were added
+    pub findings: Vec<EntryFindings>,
+}
+
+impl AuditReport {
+    /// The total number of problems found
+    pub fn total(&self) -> usize {
+        self.findings.iter().map(|entry| entry.findings.len()).sum()
+    }
+}
+
+/// Check `entries` for reused, breached, weak & old passwords,
+/// missing usernames & entries sharing a location
+pub fn audit(entries: &[Entry], options: &AuditOptions) -> Result<AuditReport> {
+    let breaches = match &options.breaches {
+        Some(path) => Some(BreachList::open(path)?),
+        None => None,
+    };
+
+    let mut by_password: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    let mut by_location: HashMap<String, Vec<usize>> = HashMap::new();
+    for (idx, entry) in entries.iter().enumerate() {
+        if !entry.password.is_empty() {
+            by_password.entry(&entry.password).or_default().push(idx);
+        }
+
+        // Other kinds of entry often share a host or issuer
+        let location = normalise_location(entry.location());
+        if entry.kind() == EntryKind::Login && !location.is_empty() {
+            by_location.entry(location).or_default().push(idx);
+        }
+    }
+
+    // The names of the other entries in the same group as `idx`
+    let others = |group: &[usize], idx: usize| -> Vec<String> {
+        group
+            .iter()
+            .filter(|&&other| other != idx)
+            .map(|&other| entries[other].reference())
+            .collect()
+    };
+
+    let now = timestamp();
+    let mut summary = Summary::default();
+    let mut findings = Vec::new();
+    for (idx, entry) in entries.iter().enumerate() {
+        let mut found = Vec::new();
+
+        if let Some(group) = by_password.get(&entry.password[..]) {
+            if group.len() > 1 {
+                summary.reused += 1;
+                found.push(Finding::Reused {
+                    with: others(group, idx),
+                });
+            }
+        }
+
+        // Some kinds of entry, like secure notes, have no password to check
+        let has_password = !entry.password.is_empty();
+
+        if let Some(count) = breaches
+            .as_ref()
+            .filter(|_| has_password)
+            .and_then(|list| list.count(&entry.password))
+        {
+            summary.breached += 1;
+            found.push(Finding::Breached { count });
+        }
+
+        let strength = entry.strength();
+        if has_password && entry.kind().checks_strength() && strength.is_weak() {
+            summary.weak += 1;
+            found.push(Finding::Weak {
+                score: strength.score,
+                label: strength.label,
+            });
+        }
+
+        if let Some(changed) = entry.password_changed().filter(|_| has_password) {
+            let days = now.saturating_sub(changed) / SECONDS_PER_DAY;
+            if days > options.max_age_days {
+                summary.old += 1;
+                found.push(Finding::Old { days });
+            }
+        }
+
+        if entry.kind() == EntryKind::Login && entry.username().trim().is_empty() {
+            summary.empty_username += 1;
+            found.push(Finding::EmptyUsername);
+        }
+
+        let group = by_location.get(&normalise_location(entry.location()));
+        if let Some(group) = group.filter(|_| entry.kind() == EntryKind::Login) {
+            if group.len() > 1 {
+                summary.duplicate_location += 1;
+                found.push(Finding::DuplicateLocation {
+                    with: others(group, idx),
+                });
+            }
+        }
+
+        if !found.is_empty() {
+            findings.push(EntryFindings {
+                entry: entry.reference(),
+                id: entry.id(),
+                findings: found,
+            });
+        }
+    }
+
+    Ok(AuditReport {
+        entries: entries.len(),
+        summary,
+        findings,
+    })
+}
+
+/// Ignore case & any scheme or trailing slash,
+/// so `https://Example.com/` is the same location as `example.com`
+fn normalise_location(location: &str) -> String {
+    let location = location.trim().to_lowercase();
+    let location = match location.split_once("://") {
+        Some((_, rest)) => rest,
+        None => &location,
+    };
+
+    location.trim_end_matches('/').to_string()
+}
@@ -1,44 +1,104 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgEnum, ArgGroup, Parser, Subcommand};
 use std::path::PathBuf;
 
+use pass_man::{
+    generator::{CharClass, GeneratorPolicy, PassphrasePolicy},
+    manager::{entry::Field, fields::FieldKind, kinds::EntryKind},
+};
+
+use crate::clipboard::ClipboardKind;
+
 #[derive(Debug, Parser)]
 #[clap(author, version, about, long_about = None)]
 pub struct Args {
     /// The action to perform
     #[clap(subcommand)]
     pub action: Action,
+    /// How to format the output
+    ///
+    /// `json` & `tsv` are meant for scripts, & their
+    /// formats are documented in the README
+    #[clap(long, global = true, arg_enum, default_value_t = Format::Plain)]
+    pub format: Format,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum Format {
+    /// Human-readable text
+    Plain,
+    /// One JSON value per command
+    Json,
+    /// Tab-separated values, one record per line
+    Tsv,
 }
 
 #[derive(Debug, Subcommand)]
 pub enum Action {
     /// Randomly generate a password
+    ///
+    /// By default, passwords are 10 characters long & use
+    /// upper & lowercase letters, with at least one of each
     Generate {
         /// The length of the password
-        #[clap(default_value_t = 10)]
-        length: u32,
-        /// Whether or not to allow numbers in the password
-        #[clap(short, long)]
-        numbers: bool,
-        /// Whether or not to allow special characters in the password
-        #[clap(short, long)]
-        special: bool,
+        length: Option<u32>,
+        #[clap(flatten)]
+        generator: GeneratorArgs,
     },
     /// Add a password entry
     ///
-    /// Will panic if `interactive` flag is not set
-    /// & not all other fields (except file) are provided
+    /// Unless `--interactive` is given, the name is needed,
+    /// along with what the entry's `--type` uses:
+    ///
+    /// - login: `--location`, `--username` & `--password`
+    /// - note: nothing else
+    /// - card: `--location`, `--username`, `--password`,
+    ///   `--field Number=<number>` & `--field Expiry=<MM/YY>`
+    /// - ssh-key: `--location`, `--username`, `--password`
+    ///   & `--field "Private key=<key>"`
+    /// - api-token: `--location`, `--username` & `--password`
+    /// - wifi: `--location` & `--password`
+    /// - database: `--location`, `--username` & `--password`
+    ///
+    /// `--generate` can always be used instead of `--password`
+    #[clap(verbatim_doc_comment, group = generator_options())]
     Add {
         /// The name of the password entry
         name: Option<String>,
+        /// What the entry holds: `login`, `note`, `card`, `ssh-key`,
+        /// `api-token`, `wifi` or `database`
+        ///
+        /// Each type labels the username, password & location to suit it,
+        /// & may need extra fields, given with `--field`
+        #[clap(short = 't', long = "type", default_value_t = EntryKind::Login)]
+        kind: EntryKind,
+        /// A custom field to add to the entry, e.g. `--field Expiry=04/27`.
+        /// Can be given multiple times
+        #[clap(
+            long = "field",
+            value_name = "NAME=VALUE",
+            multiple_occurrences = true,
+            parse(try_from_str = parse_field)
+        )]
+        fields: Vec<(String, String)>,
         /// The username to be saved
         #[clap(short, long)]
         username: Option<String>,
-        /// The password to be saved
-        ///
-        /// Set to `!gen[length]` to generate a random password,
-        /// with '-s' disabling special chars & '-n' disabling numbers
-        #[clap(short, long)]
+        /// The password to be saved, exactly as given
+        #[clap(
+            short,
+            long,
+            conflicts_with = "generate",
+            conflicts_with_all = GENERATOR_OPTIONS
+        )]
         password: Option<String>,
+        /// Generate a random password instead of giving one,
+        /// optionally of the given length
+        ///
+        /// Takes the same options as the `generate` command
+        #[clap(short, long, value_name = "LENGTH")]
+        generate: Option<Option<u32>>,
+        #[clap(flatten)]
+        generator: GeneratorArgs,
         /// Where the password will be used
         ///
         /// e.g. The website URL
@@ -46,7 +106,7 @@ pub enum Action {
         location: Option<String>,
         /// Path to a specific file
         ///
-        /// Must be a valid JSON file
+        /// Must be a PassMan vault, or not exist yet
         #[clap(short, long)]
         file: Option<PathBuf>,
         /// Create the new entry interactively
@@ -57,11 +117,11 @@ pub enum Action {
     /// Remove a password entry. Can also use `rm`
     #[clap(alias("rm"))]
     Remove {
-        /// The name of the entry to remove
+        /// The entry to remove, by name, `<name>:<username>` or ID
         name: String,
         /// The entries file to use
         ///
-        /// Must be a valid JSON file
+        /// Must be a PassMan vault, or not exist yet
         #[clap(short, long)]
         file: Option<PathBuf>,
     },
@@ -70,34 +130,121 @@ pub enum Action {
     List {
         /// The entries file to use
         ///
-        /// Must be a valid JSON file
+        /// Must be a PassMan vault, or not exist yet
         #[clap(short, long)]
         file: Option<PathBuf>,
     },
     /// See the info in a specific entry
+    ///
+    /// The password is hidden unless `--reveal` is given
     Show {
-        /// The name of the password entry to show
+        /// The entry to show, by name, `<name>:<username>` or ID
         name: String,
         /// Path to the entries file to use
         ///
-        /// Must be a valid JSON file
+        /// Must be a PassMan vault, or not exist yet
         #[clap(short, long)]
         file: Option<PathBuf>,
         /// Copy the entry's password to your clipboard
+        ///
+        /// This is the recommended way to get at a password,
+        /// since it never appears on screen. The clipboard is cleared
+        /// after 45 seconds, which can be changed with `config clipboard-timeout`
         #[clap(short, long)]
         copy: bool,
+        /// Show the password instead of hiding it
+        ///
+        /// The password will stay visible in your terminal's scrollback
+        #[clap(short, long)]
+        reveal: bool,
+    },
+    /// Copy a field of an entry to your clipboard
+    ///
+    /// Like `show --copy`, the clipboard is cleared after a timeout
+    Copy {
+        /// The entry to copy from, by name, `<name>:<username>` or ID
+        name: String,
+        /// The field to copy: `username`, `password`, `location`,
+        /// `otp` or `note:<id>`
+        #[clap(long, default_value_t = Field::Password)]
+        field: Field,
+        /// Copy the username, wait for Enter to be pressed,
+        /// then copy the password
+        #[clap(short, long, conflicts_with = "field")]
+        sequence: bool,
+        /// Path to the entries file to use
+        ///
+        /// Must be a PassMan vault, or not exist yet
+        #[clap(short, long)]
+        file: Option<PathBuf>,
     },
     /// Edit a password entry
     ///
-    /// Leave fields blank to leave them unchanged.
-    /// Random password generation works the same as
-    /// in the `add` command
+    /// Leave fields blank to leave them unchanged
+    #[clap(group = generator_options())]
     Edit {
-        /// The name of the entry to edit
+        /// The entry to edit, by name, `<name>:<username>` or ID
         name: String,
+        /// Replace the password with a randomly generated one,
+        /// optionally of the given length, instead of being asked for one
+        ///
+        /// Takes the same options as the `generate` command
+        #[clap(short, long, value_name = "LENGTH")]
+        generate: Option<Option<u32>>,
+        #[clap(flatten)]
+        generator: GeneratorArgs,
         /// The path to the entries file to use
         ///
-        /// Must be a valid JSON file
+        /// Must be a PassMan vault, or not exist yet
+        #[clap(short, long)]
+        file: Option<PathBuf>,
+    },
+    /// Show the current two-factor authentication code for an entry
+    ///
+    /// For counter-based (HOTP) secrets, each code is only shown once
+    Otp {
+        /// The entry, by name, `<name>:<username>` or ID
+        name: String,
+        /// Copy the code to your clipboard instead of showing it
+        #[clap(short, long)]
+        copy: bool,
+        /// Save the entry's secret, as a base32 string or an `otpauth://` URI,
+        /// instead of showing a code
+        ///
+        /// You'll be asked for the secret if it isn't given
+        #[clap(long, value_name = "SECRET", conflicts_with_all = &["copy", "remove"])]
+        set: Option<Option<String>>,
+        /// Make the secret given to `--set` generate counter-based (HOTP) codes,
+        /// starting from this counter, instead of time-based ones
+        #[clap(long, requires = "set")]
+        counter: Option<u64>,
+        /// Remove the entry's secret
+        #[clap(long, conflicts_with = "copy")]
+        remove: bool,
+        /// Path to the entries file to use
+        ///
+        /// Must be a PassMan vault, or not exist yet
+        #[clap(short, long)]
+        file: Option<PathBuf>,
+    },
+    /// Check the vault for reused, weak & old passwords,
+    /// entries without a username & entries sharing a location
+    ///
+    /// Exits with an error if more problems are found than `--threshold`
+    Audit {
+        /// Report passwords that haven't been changed in this many days
+        #[clap(long, value_name = "DAYS", default_value_t = 365)]
+        max_age: u64,
+        /// How many problems are allowed before exiting with an error
+        #[clap(long, value_name = "COUNT", default_value_t = 0)]
+        threshold: usize,
+        /// Also check passwords against a downloaded copy of the
+        /// Pwned Passwords list of SHA-1 hashes, ordered by hash
+        #[clap(long, value_name = "PATH")]
+        breaches: Option<PathBuf>,
+        /// Path to the entries file to use
+        ///
+        /// Must be a PassMan vault, or not exist yet
         #[clap(short, long)]
         file: Option<PathBuf>,
     },
@@ -106,11 +253,28 @@ pub enum Action {
         #[clap(subcommand)]
         subcmd: NotesSubcmd,
     },
+    /// Work with entries' custom fields, like PINs or security questions
+    Field {
+        #[clap(subcommand)]
+        subcmd: FieldSubcmd,
+    },
     /// Edit configuration options
     Config {
         #[clap(subcommand)]
         option: ConfigField,
     },
+    /// Clear the clipboard after a delay, if it still holds the text given on stdin.
+    ///
+    /// Used internally by `--copy`
+    #[clap(hide = true)]
+    ClearClipboard {
+        /// Seconds to wait before clearing
+        #[clap(long)]
+        after: u64,
+        /// The clipboard backend the text was copied with
+        #[clap(long, arg_enum)]
+        backend: ClipboardKind,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -124,7 +288,7 @@ pub enum NotesSubcmd {
         entry: String,
         /// Path to the file to use
         ///
-        /// Must be a valid JSON file
+        /// Must be a PassMan vault, or not exist yet
         #[clap(short, long)]
         file: Option<PathBuf>,
     },
@@ -140,7 +304,7 @@ pub enum NotesSubcmd {
         id: usize,
         /// Path to the entries file to use
         ///
-        /// Must be a valid JSON file
+        /// Must be a PassMan vault, or not exist yet
         #[clap(short, long)]
         file: Option<PathBuf>,
     },
@@ -157,7 +321,7 @@ pub enum NotesSubcmd {
         new_note: String,
         /// Path to the entries file to use
         ///
-        /// Must be a valid JSON file
+        /// Must be a PassMan vault, or not exist yet
         #[clap(short, long)]
         file: Option<PathBuf>,
     },
@@ -169,7 +333,75 @@ pub enum NotesSubcmd {
         entry: String,
         /// Path to the file to use
         ///
-        /// Must be a valid JSON file
+        /// Must be a PassMan vault, or not exist yet
+        #[clap(short, long)]
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum FieldSubcmd {
+    /// Add a custom field to an entry
+    Add {
+        /// The entry to add the field to
+        entry: String,
+        /// The name of the field
+        name: String,
+        /// The value of the field.
+        /// You'll be asked for it if it isn't given
+        value: Option<String>,
+        /// The type of the field: `text`, `hidden`, `url`, `email`, `number` or `date`
+        ///
+        /// Hidden fields are masked like passwords
+        #[clap(short = 't', long = "type", default_value_t = FieldKind::Text)]
+        kind: FieldKind,
+        /// Path to the entries file to use
+        ///
+        /// Must be a PassMan vault, or not exist yet
+        #[clap(short, long)]
+        file: Option<PathBuf>,
+    },
+    /// Change the value of a custom field
+    Set {
+        /// The entry with the field to change
+        entry: String,
+        /// The name of the field
+        name: String,
+        /// The new value of the field.
+        /// You'll be asked for it if it isn't given
+        value: Option<String>,
+        /// Path to the entries file to use
+        ///
+        /// Must be a PassMan vault, or not exist yet
+        #[clap(short, long)]
+        file: Option<PathBuf>,
+    },
+    /// Remove a custom field from an entry.
+    /// Can also use `rm`
+    #[clap(alias("rm"))]
+    Remove {
+        /// The entry to remove the field from
+        entry: String,
+        /// The name of the field
+        name: String,
+        /// Path to the entries file to use
+        ///
+        /// Must be a PassMan vault, or not exist yet
+        #[clap(short, long)]
+        file: Option<PathBuf>,
+    },
+    /// List the custom fields of an entry.
+    /// Can also use `ls`
+    #[clap(alias("ls"))]
+    List {
+        /// The entry to show the fields of
+        entry: String,
+        /// Show hidden fields instead of masking them
+        #[clap(short, long)]
+        reveal: bool,
+        /// Path to the entries file to use
+        ///
+        /// Must be a PassMan vault, or not exist yet
         #[clap(short, long)]
         file: Option<PathBuf>,
     },
@@ -179,4 +411,247 @@ pub enum NotesSubcmd {
 pub enum ConfigField {
     /// Edit the default file used by PassMan
     DefaultFile { path: PathBuf },
+    /// Set how many seconds copied passwords stay on the clipboard.
+    /// Set to 0 to never clear the clipboard
+    ClipboardTimeout { seconds: u64 },
+    /// Set how PassMan accesses the clipboard.
+    ///
+    /// `auto` picks `osc52` over SSH, then `wl-copy` or `xclip` if installed,
+    /// & otherwise the system clipboard
+    ClipboardBackend {
+        #[clap(arg_enum)]
+        backend: ClipboardKind,
+    },
+    /// Save password generator options as a named profile,
+    /// for use with `generate --profile`.
+    ///
+    /// Updates the profile if it already exists
+    Profile {
+        /// The name of the profile
+        name: String,
+        /// The length of the password
+        length: Option<u32>,
+        #[clap(flatten)]
+        policy: PolicyArgs,
+    },
+    /// Delete a password generator profile
+    RemoveProfile {
+        /// The name of the profile
+        name: String,
+    },
+}
+
+/// Parse a `NAME=VALUE` pair for `add --field`
+fn parse_field(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((name, value)) => Ok((name.trim().to_string(), value.to_string())),
+        None => Err("expected `NAME=VALUE`".to_string()),
+    }
+}
+
+/// The options from [`GeneratorArgs`], which `add` & `edit`
+/// only accept along with `--generate`
+const GENERATOR_OPTIONS: &[&str] = &[
+    "numbers",
+    "special",
+    "no-lowercase",
+    "no-uppercase",
+    "min-lowercase",
+    "min-uppercase",
+    "min-numbers",
+    "min-special",
+    "symbols",
+    "alphabet",
+    "no-ambiguous",
+    "exclude",
+    "profile",
+    // The other passphrase options already need this
+    "words",
+];
+
+fn generator_options() -> ArgGroup<'static> {
+    ArgGroup::new("generator-options")
+        .multiple(true)
+        .requires("generate")
+        .args(GENERATOR_OPTIONS)
+}
+
+/// Everything that controls how a password is generated
+#[derive(Debug, clap::Args)]
+pub struct GeneratorArgs {
+    #[clap(flatten)]
+    pub policy: PolicyArgs,
+    /// Start from the options saved in a profile,
+    /// which any other options override.
+    /// Profiles are saved with `config profile`
+    #[clap(short = 'P', long)]
+    pub profile: Option<String>,
+    #[clap(flatten)]
+    pub passphrase: PassphraseArgs,
+}
+
+/// Options for generating a passphrase instead of a password
+#[derive(Debug, clap::Args)]
+pub struct PassphraseArgs {
+    /// Generate a passphrase of this many random words
+    /// from the EFF's long word list, instead of a password.
+    ///
+    /// Other options besides the passphrase ones are ignored
+    #[clap(short, long, value_name = "COUNT")]
+    words: Option<u32>,
+    /// What to put between the words of a passphrase
+    #[clap(long, default_value = "-", requires = "words")]
+    separator: String,
+    /// Capitalise each word of a passphrase
+    #[clap(long, requires = "words")]
+    capitalize: bool,
+    /// Add a random digit to one of the words of a passphrase
+    #[clap(long, requires = "words")]
+    with_number: bool,
+    /// A file of words to use instead of the EFF's list, one per line
+    #[clap(long, value_name = "PATH", requires = "words")]
+    wordlist: Option<PathBuf>,
+}
+
+impl PassphraseArgs {
+    /// The passphrase options, if a passphrase was asked for
+    pub fn policy(self) -> Option<PassphrasePolicy> {
+        Some(PassphrasePolicy {
+            words: self.words?,
+            separator: self.separator,
+            capitalize: self.capitalize,
+            include_number: self.with_number,
+            wordlist: self.wordlist,
+        })
+    }
+}
+
+/// Options for the password generator
+#[derive(Debug, clap::Args)]
+pub struct PolicyArgs {
+    /// Whether or not to allow numbers in the password
+    #[clap(short, long)]
+    numbers: bool,
+    /// Whether or not to allow special characters in the password
+    #[clap(short, long)]
+    special: bool,
+    /// Don't use lowercase letters
+    #[clap(long)]
+    no_lowercase: bool,
+    /// Don't use uppercase letters
+    #[clap(long)]
+    no_uppercase: bool,
+    /// Use at least this many lowercase letters
+    #[clap(long, value_name = "COUNT")]
+    min_lowercase: Option<u32>,
+    /// Use at least this many uppercase letters
+    #[clap(long, value_name = "COUNT")]
+    min_uppercase: Option<u32>,
+    /// Use at least this many numbers. Implies `--numbers`
+    #[clap(long, value_name = "COUNT")]
+    min_numbers: Option<u32>,
+    /// Use at least this many special characters. Implies `--special`
+    #[clap(long, value_name = "COUNT")]
+    min_special: Option<u32>,
+    /// The special characters to use instead of `!@#$%^&*`.
+    /// Implies `--special`
+    #[clap(long, value_name = "CHARS")]
+    symbols: Option<String>,
+    /// Only use these characters, ignoring all other character options
+    #[clap(long, value_name = "CHARS")]
+    alphabet: Option<String>,
+    /// Leave out characters that are easily confused (`0O1lI`)
+    #[clap(long)]
+    no_ambiguous: bool,
+    /// Leave out these characters
+    #[clap(long, value_name = "CHARS")]
+    exclude: Option<String>,
+}
+
+impl PolicyArgs {
+    /// Override the parts of `policy` that were given on the command line
+    pub fn apply(self, policy: &mut GeneratorPolicy) {
+        if self.numbers {
+            policy.numbers = CharClass::REQUIRED;
+        }
+        if self.special {
+            policy.special = CharClass::REQUIRED;
+        }
+        if self.no_lowercase {
+            policy.lowercase = CharClass::DISABLED;
+        }
+        if self.no_uppercase {
+            policy.uppercase = CharClass::DISABLED;
+        }
+
+        for (min, class) in [
+            (self.min_lowercase, &mut policy.lowercase),
+            (self.min_uppercase, &mut policy.uppercase),
+            (self.min_numbers, &mut policy.numbers),
+            (self.min_special, &mut policy.special),
+        ] {
+            if let Some(min) = min {
+                *class = CharClass { enabled: true, min };
+            }
+        }
+
+        if let Some(symbols) = self.symbols {
+            policy.symbols = symbols;
+            if !policy.special.enabled {
+                policy.special = CharClass::REQUIRED;
+            }
+        }
+        if self.alphabet.is_some() {
+            policy.alphabet = self.alphabet;
+        }
+        if self.no_ambiguous {
+            policy.exclude_ambiguous = true;
+        }
+        if let Some(exclude) = self.exclude {
+            policy.exclude = exclude;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pass_man::generator::generate_with;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    use super::*;
+
+    #[test]
+    fn symbols_imply_special() {
+        let args = Args::parse_from(["passman", "generate", "4", "--symbols", "+-"]);
+        let Action::Generate { generator, .. } = args.action else {
+            panic!("expected the generate command");
+        };
+
+        let mut policy = GeneratorPolicy {
+            length: 4,
+            ..Default::default()
+        };
+        generator.policy.apply(&mut policy);
+        assert_eq!(policy.special, CharClass::REQUIRED);
+
+        for seed in 0..300 {
+            let password = generate_with(&policy, &mut ChaCha20Rng::seed_from_u64(seed)).unwrap();
+            assert!(password.contains(['+', '-']), "{password}");
+        }
+    }
+
+    #[test]
+    fn generator_options_need_generate() {
+        let parses = |args: &[&str]| Args::try_parse_from(["passman"].iter().chain(args)).is_ok();
+
+        assert!(parses(&["generate", "--numbers"]));
+        assert!(parses(&["add", "GitHub", "-g", "--numbers"]));
+        assert!(parses(&["edit", "GitHub", "-g", "20", "--words", "4"]));
+
+        assert!(!parses(&["add", "GitHub", "--numbers"]));
+        assert!(!parses(&["add", "GitHub", "-p", "hunter2", "--numbers"]));
+        assert!(!parses(&["add", "GitHub", "-p", "hunter2", "-P", "strict"]));
+        assert!(!parses(&["edit", "GitHub", "--words", "4"]));
+    }
 }
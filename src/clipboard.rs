@@ -0,0 +1,338 @@
+use std::{
+    env,
+    fs::OpenOptions,
+    io::{self, Read, Write},
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use arboard::Clipboard;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use clap::ArgEnum;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::app_error::{AppError, Result};
+
+/// Which way of accessing the clipboard to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ArgEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClipboardKind {
+    /// Pick the most suitable backend for the current environment
+    #[default]
+    Auto,
+    /// The system clipboard, accessed directly.
+    /// Needs a graphical session
+    Arboard,
+    /// OSC 52 terminal escape sequences, which work over SSH
+    /// as long as the terminal supports them
+    Osc52,
+    /// The `wl-copy` & `wl-paste` commands, for Wayland
+    WlCopy,
+    /// The `xclip` command, for X11
+    Xclip,
+}
+
+/// Somewhere text can be copied to & read back from
+pub trait ClipboardBackend {
+    /// The current contents of the clipboard,
+    /// or `None` if this backend can't read it
+    fn get_text(&mut self) -> Result<Option<String>>;
+    fn set_text(&mut self, text: &str) -> Result<()>;
+}
+
+/// The system clipboard, via `arboard`
+pub struct SystemClipboard(Clipboard);
+
+impl ClipboardBackend for SystemClipboard {
+    fn get_text(&mut self) -> Result<Option<String>> {
+        Ok(Some(self.0.get_text()?))
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<()> {
+        Ok(self.0.set_text(text.to_string())?)
+    }
+}
+
+/// Asks the terminal to set the clipboard through an OSC 52 escape sequence.
+/// Write-only, since few terminals allow reading the clipboard this way
+pub struct Osc52;
+
+impl ClipboardBackend for Osc52 {
+    fn get_text(&mut self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<()> {
+        let mut sequence = Zeroizing::new(format!("\x1b]52;c;{}\x07", BASE64.encode(text)));
+        if env::var_os("TMUX").is_some() {
+            // tmux only passes escape sequences through to
+            // the outer terminal when they're wrapped like this
+            *sequence = format!("\x1bPtmux;\x1b{}\x1b\\", *sequence);
+        }
+
+        match OpenOptions::new().write(true).open("/dev/tty") {
+            Ok(mut tty) => tty.write_all(sequence.as_bytes())?,
+            Err(_) => {
+                let mut stdout = io::stdout();
+                stdout.write_all(sequence.as_bytes())?;
+                stdout.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An external program that reads the text to copy from stdin,
+/// & optionally one that prints the clipboard's contents
+pub struct ExternalCommand {
+    copy: &'static [&'static str],
+    paste: &'static [&'static str],
+}
+
+impl ExternalCommand {
+    const WL_COPY: Self = Self {
+        copy: &["wl-copy"],
+        paste: &["wl-paste", "--no-newline"],
+    };
+    const XCLIP: Self = Self {
+        copy: &["xclip", "-selection", "clipboard", "-in"],
+        paste: &["xclip", "-selection", "clipboard", "-out"],
+    };
+
+    fn run(args: &[&str], input: Option<&str>) -> Result<Zeroizing<Vec<u8>>> {
+        let mut child = Command::new(args[0])
+            .args(&args[1..])
+            .stdin(if input.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(if input.is_none() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+            stdin.write_all(input.as_bytes())?;
+        }
+
+        let mut output = Zeroizing::new(Vec::new());
+        if input.is_none() {
+            if let Some(mut stdout) = child.stdout.take() {
+                stdout.read_to_end(&mut output)?;
+            }
+        }
+
+        if !child.wait()?.success() {
+            return Err(AppError::ClipboardCommandFailed {
+                command: args.join(" "),
+            });
+        }
+
+        Ok(output)
+    }
+}
+
+impl ClipboardBackend for ExternalCommand {
+    fn get_text(&mut self) -> Result<Option<String>> {
+        let output = Self::run(self.paste, None)?;
+
+        Ok(Some(String::from_utf8_lossy(&output).into_owned()))
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<()> {
+        Self::run(self.copy, Some(text))?;
+
+        Ok(())
+    }
+}
+
+impl ClipboardKind {
+    /// Resolve [`ClipboardKind::Auto`] into a specific backend
+    pub fn detect(self) -> Self {
+        if self != ClipboardKind::Auto {
+            return self;
+        }
+
+        let has_var = |name| env::var_os(name).is_some();
+
+        if has_var("SSH_CONNECTION") || has_var("SSH_TTY") {
+            ClipboardKind::Osc52
+        } else if has_var("WAYLAND_DISPLAY") && on_path("wl-copy") {
+            ClipboardKind::WlCopy
+        } else if has_var("DISPLAY") && on_path("xclip") {
+            ClipboardKind::Xclip
+        } else if cfg!(any(windows, target_os = "macos")) || has_var("DISPLAY") {
+            ClipboardKind::Arboard
+        } else {
+            ClipboardKind::Osc52
+        }
+    }
+
+    /// Open the clipboard using this backend
+    pub fn open(self) -> Result<Box<dyn ClipboardBackend>> {
+        Ok(match self.detect() {
+            ClipboardKind::Auto => unreachable!("`detect` never returns `Auto`"),
+            ClipboardKind::Arboard => Box::new(SystemClipboard(Clipboard::new()?)),
+            ClipboardKind::Osc52 => Box::new(Osc52),
+            ClipboardKind::WlCopy => Box::new(ExternalCommand::WL_COPY),
+            ClipboardKind::Xclip => Box::new(ExternalCommand::XCLIP),
+        })
+    }
+}
+
+/// Returns `true` if `program` is in one of the directories in `PATH`
+fn on_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+/// Put `text` on the clipboard using the given backend
+pub fn copy(kind: ClipboardKind, text: &str) -> Result<()> {
+    kind.open()?.set_text(text)
+}
+
+/// Clear `clipboard` if it still contains `secret`,
+/// returning whether it was cleared.
+///
+/// Anything copied since `secret` was is left alone.
+/// If the backend can't read the clipboard, it's cleared regardless
+pub fn clear_if_unchanged(clipboard: &mut dyn ClipboardBackend, secret: &str) -> Result<bool> {
+    if let Some(current) = clipboard.get_text()?.map(Zeroizing::new) {
+        if *current != secret {
+            return Ok(false);
+        }
+    }
+
+    clipboard.set_text("")?;
+
+    Ok(true)
+}
+
+/// Start a background process that clears `secret` from the clipboard
+/// after `timeout` seconds, so PassMan can exit straight away.
+///
+/// The secret is passed to the helper over a pipe,
+/// so it never shows up in its arguments
+pub fn clear_after(kind: ClipboardKind, secret: &str, timeout: u64) -> Result<()> {
+    let backend = kind
+        .detect()
+        .to_possible_value()
+        .expect("no backends are skipped")
+        .get_name();
+
+    let mut cmd = Command::new(env::current_exe()?);
+    cmd.args([
+        "clear-clipboard",
+        "--after",
+        &timeout.to_string(),
+        "--backend",
+        backend,
+    ])
+    .stdin(Stdio::piped())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null());
+    detach(&mut cmd);
+
+    let mut child = cmd.spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(secret.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// The helper started by [`clear_after`]
+pub fn run_clear_helper(kind: ClipboardKind, timeout: u64) -> Result<()> {
+    let mut secret = Zeroizing::new(String::new());
+    io::stdin().read_to_string(&mut secret)?;
+
+    thread::sleep(Duration::from_secs(timeout));
+    clear_if_unchanged(kind.open()?.as_mut(), &secret)?;
+
+    Ok(())
+}
+
+/// Stop the helper from being killed along with
+/// the terminal's foreground process group (e.g. by Ctrl+C)
+#[cfg(unix)]
+fn detach(cmd: &mut Command) {
+    use std::os::unix::process::CommandExt;
+
+    cmd.process_group(0);
+}
+
+#[cfg(windows)]
+fn detach(cmd: &mut Command) {
+    use std::os::windows::process::CommandExt;
+
+    const DETACHED_PROCESS: u32 = 0x0000_0008;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+    cmd.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+}
+
+#[cfg(not(any(unix, windows)))]
+fn detach(_cmd: &mut Command) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A clipboard that only exists in memory,
+    /// optionally write-only like OSC 52
+    struct MemoryClipboard {
+        text: String,
+        readable: bool,
+    }
+
+    impl ClipboardBackend for MemoryClipboard {
+        fn get_text(&mut self) -> Result<Option<String>> {
+            Ok(self.readable.then(|| self.text.clone()))
+        }
+
+        fn set_text(&mut self, text: &str) -> Result<()> {
+            self.text = text.to_string();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn clears_unchanged_secret() {
+        let mut clipboard = MemoryClipboard {
+            text: "hunter2".to_string(),
+            readable: true,
+        };
+
+        assert!(clear_if_unchanged(&mut clipboard, "hunter2").unwrap());
+        assert_eq!(clipboard.text, "");
+    }
+
+    #[test]
+    fn keeps_newer_copies() {
+        let mut clipboard = MemoryClipboard {
+            text: "hunter2".to_string(),
+            readable: true,
+        };
+        clipboard.set_text("something else").unwrap();
+
+        assert!(!clear_if_unchanged(&mut clipboard, "hunter2").unwrap());
+        assert_eq!(clipboard.text, "something else");
+    }
+
+    #[test]
+    fn clears_write_only_clipboards() {
+        let mut clipboard = MemoryClipboard {
+            text: "something else".to_string(),
+            readable: false,
+        };
+
+        assert!(clear_if_unchanged(&mut clipboard, "hunter2").unwrap());
+        assert_eq!(clipboard.text, "");
+    }
+}
@@ -1,16 +1,30 @@
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf};
 
+use pass_man::generator::GeneratorPolicy;
 use serde::{Deserialize, Serialize};
 
+use crate::clipboard::ClipboardKind;
+
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub(crate) file: PathBuf,
+    /// Seconds to wait before clearing a copied password
+    /// from the clipboard. `0` disables clearing
+    pub(crate) clipboard_timeout: u64,
+    /// How to access the clipboard
+    pub(crate) clipboard_backend: ClipboardKind,
+    /// Named password generator options
+    pub(crate) profiles: BTreeMap<String, GeneratorPolicy>,
 }
 
 impl Default for Config {
     fn default() -> Config {
         Config {
             file: default_path(),
+            clipboard_timeout: 45,
+            clipboard_backend: ClipboardKind::Auto,
+            profiles: BTreeMap::new(),
         }
     }
 }
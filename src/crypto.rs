@@ -0,0 +1,163 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::error::{Error, Result};
+
+/// The current version of the encrypted file header
+const HEADER_VERSION: u32 = 1;
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+/// Everything needed to re-derive the key for a vault,
+/// apart from the master password itself.
+///
+/// Also used as the associated data for the cipher,
+/// so tampering with it causes decryption to fail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    kdf: KdfParams,
+}
+
+/// Argon2id parameters, stored so they can be
+/// tuned without breaking existing vaults
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KdfParams {
+    algorithm: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    salt: String,
+}
+
+/// The on-disk representation of an encrypted vault
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedFile {
+    #[serde(flatten)]
+    header: Header,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+
+        Self {
+            algorithm: "argon2id".to_string(),
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+            salt: BASE64.encode(salt),
+        }
+    }
+}
+
+impl KdfParams {
+    fn derive_key(&self, password: &str) -> Result<Zeroizing<[u8; KEY_LEN]>> {
+        if self.algorithm != "argon2id" {
+            return Err(Error::UnsupportedKdf {
+                name: self.algorithm.clone(),
+            });
+        }
+
+        let params = Params::new(
+            self.memory_kib,
+            self.iterations,
+            self.parallelism,
+            Some(KEY_LEN),
+        )?;
+        let salt = BASE64.decode(&self.salt).map_err(|_| Error::CorruptVault)?;
+
+        let mut key = Zeroizing::new([0u8; KEY_LEN]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params).hash_password_into(
+            password.as_bytes(),
+            &salt,
+            key.as_mut(),
+        )?;
+
+        Ok(key)
+    }
+}
+
+/// Returns `true` if `data` looks like an encrypted vault
+/// rather than a plain JSON array of entries
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.iter()
+        .find(|b| !b.is_ascii_whitespace())
+        .is_some_and(|&b| b == b'{')
+}
+
+/// Encrypt `plaintext` with a key derived from `password`,
+/// returning the bytes to write to disk
+pub fn encrypt(plaintext: &[u8], password: &str) -> Result<Vec<u8>> {
+    let header = Header {
+        version: HEADER_VERSION,
+        kdf: KdfParams::default(),
+    };
+    let key = header.kdf.derive_key(password)?;
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key.as_ref()));
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let aad = serde_json::to_vec(&header)?;
+
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| Error::CorruptVault)?;
+
+    let file = EncryptedFile {
+        header,
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    };
+
+    Ok(serde_json::to_vec(&file)?)
+}
+
+/// Decrypt a vault previously written by [`encrypt`].
+/// The plaintext is wiped from memory when dropped
+pub fn decrypt(data: &[u8], password: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let file: EncryptedFile = serde_json::from_slice(data)?;
+    if file.header.version != HEADER_VERSION {
+        return Err(Error::UnsupportedVersion {
+            version: file.header.version,
+        });
+    }
+
+    let key = file.header.kdf.derive_key(password)?;
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key.as_ref()));
+    let nonce = BASE64
+        .decode(&file.nonce)
+        .map_err(|_| Error::CorruptVault)?;
+    if nonce.len() != 24 {
+        return Err(Error::CorruptVault);
+    }
+    let ciphertext = BASE64
+        .decode(&file.ciphertext)
+        .map_err(|_| Error::CorruptVault)?;
+    let aad = serde_json::to_vec(&file.header)?;
+
+    cipher
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: &aad,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| Error::IncorrectPassword)
+}
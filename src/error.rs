@@ -1,19 +1,105 @@
+/// Everything that can go wrong while working with a vault
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("Entry `{name}` already exists")]
     EntryExists { name: String },
     #[error("Entry `{name}` does not exist")]
     EntryDoesntExist { name: String },
+    #[error(
+        "`{name}` could mean any of `{}`. Use `<name>:<username>` or the entry's ID to pick one",
+        .matches.join("`, `")
+    )]
+    AmbiguousEntry { name: String, matches: Vec<String> },
     #[error("Note ID was {id} but there are only {len} notes")]
     NoteIdOOB { id: usize, len: usize },
+    #[error("`{field}` is not a valid field. Expected `username`, `password`, `location`, `otp` or `note:<id>`")]
+    InvalidField { field: String },
+    #[error("`{kind}` is not a valid field type. Expected `text`, `hidden`, `url`, `email`, `number` or `date`")]
+    InvalidFieldKind { kind: String },
+    #[error("`{value}` is not a valid {kind}")]
+    InvalidFieldValue {
+        kind: crate::manager::fields::FieldKind,
+        value: String,
+    },
+    #[error("Entry `{entry}` already has a field called `{field}`")]
+    CustomFieldExists { entry: String, field: String },
+    #[error("Entry `{entry}` has no field called `{field}`")]
+    CustomFieldDoesntExist { entry: String, field: String },
+    #[error("`{kind}` is not a valid entry type. Expected `login`, `note`, `card`, `ssh-key`, `api-token`, `wifi` or `database`")]
+    InvalidEntryKind { kind: String },
+    #[error("Invalid `{field}` field: {reason}")]
+    InvalidEntryField { field: String, reason: String },
+    #[error("{kind} entries need the `{field}` field")]
+    MissingField { kind: String, field: String },
+    #[error("Invalid password generator options: {reason}")]
+    InvalidPolicy { reason: String },
+    #[error("Password generator profile `{name}` does not exist")]
+    ProfileDoesntExist { name: String },
+    #[error("Incorrect master password, or the vault has been tampered with")]
+    IncorrectPassword,
+    #[error("The master passwords didn't match, so the vault was left as it was")]
+    PasswordMismatch,
+    #[error("The vault file is corrupt")]
+    CorruptVault,
+    #[error("Unsupported vault version {version}")]
+    UnsupportedVersion { version: u32 },
+    #[error("Unsupported key derivation function `{name}`")]
+    UnsupportedKdf { name: String },
+    #[error("The vault was opened read-only")]
+    ReadOnly,
+    #[error("Timed out waiting for another PassMan process to release `{}`", .path.display())]
+    LockTimeout { path: std::path::PathBuf },
+    #[error("Invalid one-time password secret: {reason}")]
+    InvalidOtp { reason: String },
+    #[error("Entry `{name}` has no one-time password secret")]
+    NoOtp { name: String },
+    #[error("`{}` is not a Pwned Passwords file of SHA-1 hashes", .path.display())]
+    InvalidBreachList { path: std::path::PathBuf },
+    #[error("The audit found {findings} problems, more than the limit of {threshold}")]
+    AuditFailed { findings: usize, threshold: usize },
+    #[error(transparent)]
+    KdfErr(#[from] argon2::Error),
     #[error(transparent)]
     IOErr(#[from] std::io::Error),
     #[error(transparent)]
     JSONErr(#[from] serde_json::Error),
-    #[error(transparent)]
-    ClipboardErr(#[from] arboard::Error),
-    #[error(transparent)]
-    ConfigErr(#[from] confy::ConfyError),
+}
+
+impl Error {
+    /// A short, stable identifier for the kind of error,
+    /// for use in machine-readable output
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::EntryExists { .. } => "entry_exists",
+            Error::EntryDoesntExist { .. } => "entry_doesnt_exist",
+            Error::AmbiguousEntry { .. } => "ambiguous_entry",
+            Error::NoteIdOOB { .. } => "note_id_out_of_bounds",
+            Error::InvalidField { .. } => "invalid_field",
+            Error::InvalidFieldKind { .. } => "invalid_field_type",
+            Error::InvalidFieldValue { .. } => "invalid_field_value",
+            Error::CustomFieldExists { .. } => "custom_field_exists",
+            Error::CustomFieldDoesntExist { .. } => "custom_field_doesnt_exist",
+            Error::InvalidEntryKind { .. } => "invalid_entry_type",
+            Error::InvalidEntryField { .. } => "invalid_entry_field",
+            Error::MissingField { .. } => "missing_field",
+            Error::InvalidPolicy { .. } => "invalid_policy",
+            Error::ProfileDoesntExist { .. } => "profile_doesnt_exist",
+            Error::IncorrectPassword => "incorrect_password",
+            Error::PasswordMismatch => "password_mismatch",
+            Error::CorruptVault => "corrupt_vault",
+            Error::UnsupportedVersion { .. } => "unsupported_version",
+            Error::UnsupportedKdf { .. } => "unsupported_kdf",
+            Error::ReadOnly => "read_only",
+            Error::LockTimeout { .. } => "lock_timeout",
+            Error::InvalidOtp { .. } => "invalid_otp",
+            Error::NoOtp { .. } => "no_otp",
+            Error::InvalidBreachList { .. } => "invalid_breach_list",
+            Error::AuditFailed { .. } => "audit_failed",
+            Error::KdfErr(_) => "kdf",
+            Error::IOErr(_) => "io",
+            Error::JSONErr(_) => "json",
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
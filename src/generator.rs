@@ -1,27 +1,400 @@
-use rand::Rng;
-
-const LETTERS: &str = "\
-ABCDEFGHIJKLMNOPQRSTUVWXYZ\
-abcdefghijklmnopqrstuvwxyz";
-const NUMBERS: &str = "0123456789";
-const SPECIAL_CHARS: &str = "!@#$%^&*";
-
-pub fn generate_pw(length: u32, numbers_allowed: bool, special_chars_allowed: bool) -> String {
-    let char_set = format!(
-        "{}{}{}",
-        LETTERS,
-        if numbers_allowed { NUMBERS } else { "" },
-        if special_chars_allowed {
-            SPECIAL_CHARS
+use std::{fs, path::PathBuf};
+
+use rand::{rngs::OsRng, seq::SliceRandom, CryptoRng, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::error::{Error, Result};
+
+pub const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+pub const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+pub const NUMBERS: &str = "0123456789";
+pub const SPECIAL_CHARS: &str = "!@#$%^&*";
+/// Characters that are easily mistaken for one another
+pub const AMBIGUOUS_CHARS: &str = "0O1lI";
+/// The EFF's long word list for passphrases, with 7776 words
+/// (<https://www.eff.org/dice>)
+pub const EFF_LONG_WORDLIST: &str = include_str!("../res/eff_large_wordlist.txt");
+
+/// Whether a class of characters can appear in a password,
+/// & how many times it must
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CharClass {
+    pub enabled: bool,
+    /// The minimum number of characters from this class.
+    /// Ignored if the class isn't enabled
+    pub min: u32,
+}
+
+impl CharClass {
+    pub const DISABLED: Self = Self {
+        enabled: false,
+        min: 0,
+    };
+
+    /// Enabled & required to appear at least once
+    pub const REQUIRED: Self = Self {
+        enabled: true,
+        min: 1,
+    };
+}
+
+/// Rules for generating a password.
+///
+/// All character sets are made of `char`s, so any Unicode characters can be used
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneratorPolicy {
+    pub length: u32,
+    /// The characters used for the special class
+    pub symbols: String,
+    /// If set, passwords are made only from these characters,
+    /// & the character classes are ignored
+    pub alphabet: Option<String>,
+    /// Leave out [`AMBIGUOUS_CHARS`]
+    pub exclude_ambiguous: bool,
+    /// Any other characters to leave out
+    pub exclude: String,
+    // The classes come last so the policy can be saved as TOML,
+    // which needs plain values before tables
+    pub lowercase: CharClass,
+    pub uppercase: CharClass,
+    pub numbers: CharClass,
+    pub special: CharClass,
+}
+
+impl Default for GeneratorPolicy {
+    /// 10 characters, with at least one uppercase & one lowercase letter
+    fn default() -> Self {
+        Self {
+            length: 10,
+            symbols: SPECIAL_CHARS.to_string(),
+            alphabet: None,
+            exclude_ambiguous: false,
+            exclude: String::new(),
+            lowercase: CharClass::REQUIRED,
+            uppercase: CharClass::REQUIRED,
+            numbers: CharClass::DISABLED,
+            special: CharClass::DISABLED,
+        }
+    }
+}
+
+impl GeneratorPolicy {
+    /// Check that passwords can actually be generated with this policy,
+    /// i.e. that the required characters fit within the length
+    /// & there are characters left to use after any exclusions
+    pub fn validate(&self) -> Result<()> {
+        let sets = self.char_sets();
+        let invalid = |reason: String| Err(Error::InvalidPolicy { reason });
+
+        let required: u32 = sets.iter().map(|(_, min)| min).sum();
+        if required > self.length {
+            return invalid(format!(
+                "the character classes need at least {required} characters, but the length is {}",
+                self.length
+            ));
+        }
+        if sets.iter().any(|(chars, min)| chars.is_empty() && *min > 0) {
+            return invalid("a required character class has no characters left to use".to_string());
+        }
+        if self.length > 0 && sets.iter().all(|(chars, _)| chars.is_empty()) {
+            return invalid("there are no characters to use".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// The characters each class can use & how many of each are required,
+    /// with any excluded characters removed
+    fn char_sets(&self) -> Vec<(Vec<char>, u32)> {
+        let excluded = |c: &char| {
+            self.exclude.contains(*c) || (self.exclude_ambiguous && AMBIGUOUS_CHARS.contains(*c))
+        };
+        let chars = |set: &str| -> Vec<char> {
+            let mut chars: Vec<char> = set.chars().filter(|c| !excluded(c)).collect();
+            chars.sort_unstable();
+            chars.dedup();
+            chars
+        };
+
+        if let Some(alphabet) = &self.alphabet {
+            return vec![(chars(alphabet), 0)];
+        }
+
+        [
+            (LOWERCASE, self.lowercase),
+            (UPPERCASE, self.uppercase),
+            (NUMBERS, self.numbers),
+            (self.symbols.as_str(), self.special),
+        ]
+        .into_iter()
+        .filter(|(_, class)| class.enabled)
+        .map(|(set, class)| (chars(set), class.min))
+        .collect()
+    }
+}
+
+/// A fresh ChaCha20 generator seeded from the operating system's CSPRNG
+fn new_rng() -> ChaCha20Rng {
+    ChaCha20Rng::from_rng(OsRng).expect("the OS random number generator failed")
+}
+
+/// Pick one item from `items` uniformly at random
+fn pick<T: Copy, R: Rng + ?Sized>(items: &[T], rng: &mut R) -> T {
+    *items.choose(rng).expect("`items` is never empty")
+}
+
+/// Generate a password following `policy`.
+///
+/// Every class with a minimum is guaranteed to appear at least that many times
+pub fn generate(policy: &GeneratorPolicy) -> Result<String> {
+    generate_with(policy, &mut new_rng())
+}
+
+/// Like [`generate`], but using the given random number generator.
+///
+/// With a seeded generator, the same password is generated every time,
+/// which is mostly useful for testing
+pub fn generate_with<R: RngCore + CryptoRng>(
+    policy: &GeneratorPolicy,
+    rng: &mut R,
+) -> Result<String> {
+    policy.validate()?;
+
+    let sets = policy.char_sets();
+    let required: u32 = sets.iter().map(|(_, min)| min).sum();
+    let all: Vec<char> = sets.iter().flat_map(|(chars, _)| chars).copied().collect();
+
+    let mut password = Zeroizing::new(Vec::with_capacity(policy.length as usize));
+    for (chars, min) in &sets {
+        for _ in 0..*min {
+            password.push(pick(chars, rng));
+        }
+    }
+    for _ in required..policy.length {
+        password.push(pick(&all, rng));
+    }
+    password.shuffle(rng);
+
+    Ok(password.iter().collect())
+}
+
+/// Generate a random password of `length` characters.
+///
+/// Letters are always used, with numbers & the special characters `!@#$%^&*`
+/// each optionally included. Every enabled class appears at least once
+pub fn generate_pw(
+    length: u32,
+    numbers_allowed: bool,
+    special_chars_allowed: bool,
+) -> Result<String> {
+    let class = |enabled| {
+        if enabled {
+            CharClass::REQUIRED
         } else {
-            ""
+            CharClass::DISABLED
+        }
+    };
+
+    generate(&GeneratorPolicy {
+        length,
+        numbers: class(numbers_allowed),
+        special: class(special_chars_allowed),
+        ..Default::default()
+    })
+}
+
+/// Rules for generating a passphrase of random words
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassphrasePolicy {
+    /// The number of words to use
+    pub words: u32,
+    /// What to put between each word
+    pub separator: String,
+    /// Capitalise the first letter of each word
+    pub capitalize: bool,
+    /// Add a random digit to the end of one of the words
+    pub include_number: bool,
+    /// A file to read words from instead of [`EFF_LONG_WORDLIST`].
+    ///
+    /// Each non-empty line is used as a word. Lines from diceware lists,
+    /// like `11111 abacus`, use only the word after the dice rolls
+    pub wordlist: Option<PathBuf>,
+}
+
+impl Default for PassphrasePolicy {
+    /// 6 lowercase words separated by `-`
+    fn default() -> Self {
+        Self {
+            words: 6,
+            separator: "-".to_string(),
+            capitalize: false,
+            include_number: false,
+            wordlist: None,
         }
-    );
+    }
+}
+
+/// Generate a passphrase following `policy`
+pub fn generate_passphrase(policy: &PassphrasePolicy) -> Result<String> {
+    generate_passphrase_with(policy, &mut new_rng())
+}
+
+/// Like [`generate_passphrase`], but using the given random number generator
+pub fn generate_passphrase_with<R: RngCore + CryptoRng>(
+    policy: &PassphrasePolicy,
+    rng: &mut R,
+) -> Result<String> {
+    if policy.words == 0 {
+        return Err(Error::InvalidPolicy {
+            reason: "a passphrase needs at least 1 word".to_string(),
+        });
+    }
+
+    let custom = match &policy.wordlist {
+        Some(path) => Some(fs::read_to_string(path)?),
+        None => None,
+    };
+    let mut wordlist: Vec<&str> = custom
+        .as_deref()
+        .unwrap_or(EFF_LONG_WORDLIST)
+        .lines()
+        .filter_map(|line| line.split_whitespace().last())
+        .collect();
+    wordlist.sort_unstable();
+    wordlist.dedup();
 
-    (0..length)
+    if wordlist.len() < 2 {
+        return Err(Error::InvalidPolicy {
+            reason: "the word list needs at least 2 different words".to_string(),
+        });
+    }
+
+    let mut words: Vec<Zeroizing<String>> = (0..policy.words)
         .map(|_| {
-            let idx = rand::thread_rng().gen_range(0..char_set.as_bytes().len());
-            char_set.as_bytes()[idx] as char
+            let word = pick(&wordlist, rng);
+            let mut chars = word.chars();
+
+            Zeroizing::new(match chars.next() {
+                Some(first) if policy.capitalize => first.to_uppercase().chain(chars).collect(),
+                _ => word.to_string(),
+            })
         })
-        .collect()
+        .collect();
+
+    if policy.include_number && !words.is_empty() {
+        let idx = rng.gen_range(0..words.len());
+        let digit = pick(&NUMBERS.chars().collect::<Vec<_>>(), rng);
+        words[idx].push(digit);
+    }
+
+    let mut passphrase = String::new();
+    for (idx, word) in words.iter().enumerate() {
+        if idx > 0 {
+            passphrase.push_str(&policy.separator);
+        }
+        passphrase.push_str(word);
+    }
+
+    Ok(passphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded(seed: u64) -> ChaCha20Rng {
+        ChaCha20Rng::seed_from_u64(seed)
+    }
+
+    fn strict_policy() -> GeneratorPolicy {
+        GeneratorPolicy {
+            length: 16,
+            lowercase: CharClass {
+                enabled: true,
+                min: 2,
+            },
+            uppercase: CharClass {
+                enabled: true,
+                min: 3,
+            },
+            numbers: CharClass {
+                enabled: true,
+                min: 4,
+            },
+            special: CharClass {
+                enabled: true,
+                min: 5,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn seeded_output_is_fixed() {
+        let policy = strict_policy();
+        assert_eq!(
+            generate_with(&policy, &mut seeded(42)).unwrap(),
+            "#C%$AnK@689#@3nq"
+        );
+        assert_eq!(
+            generate_with(&policy, &mut seeded(42)).unwrap(),
+            generate_with(&policy, &mut seeded(42)).unwrap()
+        );
+
+        let passphrase = generate_passphrase_with(&PassphrasePolicy::default(), &mut seeded(42));
+        assert_eq!(
+            passphrase.unwrap(),
+            "supermom-mothball-pummel-hatless-abroad-bunny"
+        );
+    }
+
+    #[test]
+    fn required_classes_meet_minimums() {
+        let policy = strict_policy();
+        let count =
+            |password: &str, set: &str| password.chars().filter(|&c| set.contains(c)).count();
+
+        for seed in 0..200 {
+            let password = generate_with(&policy, &mut seeded(seed)).unwrap();
+            assert_eq!(password.chars().count(), 16);
+            assert!(count(&password, LOWERCASE) >= 2, "{password}");
+            assert!(count(&password, UPPERCASE) >= 3, "{password}");
+            assert!(count(&password, NUMBERS) >= 4, "{password}");
+            assert!(count(&password, SPECIAL_CHARS) >= 5, "{password}");
+        }
+    }
+
+    #[test]
+    fn rejects_empty_passphrases() {
+        let policy = PassphrasePolicy {
+            words: 0,
+            ..Default::default()
+        };
+        assert!(matches!(
+            generate_passphrase_with(&policy, &mut seeded(0)),
+            Err(Error::InvalidPolicy { .. })
+        ));
+    }
+
+    #[test]
+    fn samples_non_ascii_alphabet() {
+        let alphabet = "äöüßλπжё🔑";
+        let policy = GeneratorPolicy {
+            length: 32,
+            alphabet: Some(alphabet.to_string()),
+            ..Default::default()
+        };
+
+        let mut seen = String::new();
+        for seed in 0..20 {
+            let password = generate_with(&policy, &mut seeded(seed)).unwrap();
+            assert_eq!(password.chars().count(), 32);
+            assert!(password.chars().all(|c| alphabet.contains(c)), "{password}");
+            seen.push_str(&password);
+        }
+        assert!(alphabet.chars().all(|c| seen.contains(c)));
+    }
 }
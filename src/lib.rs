@@ -0,0 +1,32 @@
+//! The vault & password generation logic behind the `passman` command-line tool,
+//! for use by other frontends.
+//!
+//! ```no_run
+//! use pass_man::{Entry, Vault};
+//!
+//! let mut vault = Vault::open("passwords.json", "master password")?;
+//! vault.add(Entry::new(
+//!     "GitHub".to_string(),
+//!     "github.com".to_string(),
+//!     "octocat".to_string(),
+//!     "hunter2".to_string(),
+//! ))?;
+//! vault.save()?;
+//!
+//! for entry in vault.entries() {
+//!     println!("{} [{}]", entry.name(), entry.location());
+//! }
+//! # Ok::<(), pass_man::Error>(())
+//! ```
+
+pub mod audit;
+mod crypto;
+pub mod error;
+pub mod generator;
+pub mod manager;
+pub mod otp;
+pub mod strength;
+
+pub use error::{Error, Result};
+pub use manager::{entry::Entry, Vault};
+pub use uuid::Uuid;
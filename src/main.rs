@@ -1,118 +1,378 @@
+mod app_error;
 mod cli;
+mod clipboard;
 mod config;
-mod error;
-mod generator;
-mod manager;
+mod output;
+
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
 
 use clap::Parser;
+use pass_man::{
+    audit::{self, AuditOptions},
+    generator::{self, GeneratorPolicy},
+    manager::{
+        entry::{Field, PasswordMode},
+        fields::{CustomField, FieldKind},
+    },
+    otp::{Otp, OtpCode, OtpKind},
+    strength, Entry, Error, Vault,
+};
+use rpassword::prompt_password;
+use zeroize::Zeroizing;
 
-use cli::{Action, Args, ConfigField, NotesSubcmd};
+use app_error::{AppError, Result};
+use cli::{Action, Args, ConfigField, FieldSubcmd, Format, GeneratorArgs, NotesSubcmd};
 use config::Config;
-use error::{Error, Result};
-use manager::{entry::Entry, entry_exists, notes};
-use rpassword::prompt_password;
+use output::Renderer;
 
-fn main() -> Result<()> {
+fn main() {
     let args = Args::parse();
+    let out = output::renderer(args.format);
+    // Only ask which entry was meant when someone's there to answer
+    let prompt = args.format == Format::Plain && std::io::stdin().is_terminal();
+
+    if let Err(e) = run(args.action, out, prompt) {
+        eprint!("{}", out.error(&e));
+        std::process::exit(1);
+    }
+}
+
+fn run(action: Action, out: &dyn Renderer, prompt: bool) -> Result<()> {
     let mut config = confy::load::<Config>("PassMan")?;
-    match args.action {
-        Action::Generate {
-            length,
-            numbers,
-            special,
-        } => {
-            let pw = generator::generate_pw(length, numbers, special);
-            println!("{pw}");
+    match action {
+        Action::Generate { length, generator } => {
+            let pw = generate_password(&config, length, generator)?;
+            let strength = strength::estimate(&pw, &[]);
+            print!("{}", *Zeroizing::new(out.password(&pw, &strength)));
         }
         Action::Add {
             name,
+            kind,
+            fields,
             location,
             username,
             password,
+            generate,
+            generator,
             file,
             interactive,
         } => {
+            let generated = match generate {
+                Some(length) => Some(generate_password(&config, length, generator)?),
+                None => None,
+            };
             let file = match file {
                 Some(path) => path,
                 None => config.file,
             };
 
             let new = if !interactive {
+                // Only the parts of the entry its kind uses are needed
+                let needed =
+                    |value: Option<String>, label: Option<&str>, argument| match (value, label) {
+                        (Some(value), _) => Ok(value),
+                        (None, None) => Ok(String::new()),
+                        (None, Some(label)) => Err(AppError::MissingArgument {
+                            kind: kind.label().to_string(),
+                            part: in_sentence(label),
+                            argument,
+                        }),
+                    };
+
+                let password = match generated {
+                    Some(pw) => Some(pw.to_string()),
+                    None => password,
+                };
+                let fields = fields
+                    .into_iter()
+                    .map(|(name, value)| {
+                        let field_kind = kind
+                            .field_spec(&name)
+                            .map_or(FieldKind::Text, |spec| spec.kind);
+                        CustomField::new(name, field_kind, value)
+                    })
+                    .collect::<pass_man::Result<_>>()?;
+
                 Entry::new(
-                    name.expect("No name provided"),
-                    location.expect("No location provided"),
-                    username.expect("No username provided"),
-                    password.expect("No password provided"),
+                    needed(name, Some("Name"), "the `NAME` argument")?,
+                    needed(location, kind.location_label(), "`--location`")?,
+                    needed(username, kind.username_label(), "`--username`")?,
+                    needed(
+                        password,
+                        kind.password_label(),
+                        "`--password` or `--generate`",
+                    )?,
                 )
+                .with_kind(kind)
+                .with_fields(fields)
             } else {
+                let prompt = |label: Option<&str>| match label {
+                    Some(label) => {
+                        get_input::<String>(&format!("Enter the {}: ", in_sentence(label)))
+                            .trim()
+                            .to_string()
+                    }
+                    None => String::new(),
+                };
+
                 let name = get_input::<String>("Enter a name: ").trim().to_string();
-                let location = get_input::<String>("Enter a location: ").trim().to_string();
-                let username = get_input::<String>("Enter a username: ").trim().to_string();
-                let password = prompt_password("Enter a password: ")?;
+                let location = prompt(kind.location_label());
+                let username = prompt(kind.username_label());
+                let password = match (generated, kind.password_label()) {
+                    (Some(pw), _) => pw.to_string(),
+                    (None, Some(label)) => {
+                        prompt_password(format!("Enter the {}: ", in_sentence(label)))?
+                    }
+                    (None, None) => String::new(),
+                };
+
+                let mut fields = Vec::new();
+                for spec in kind.schema() {
+                    let optional = if spec.required { "" } else { " (optional)" };
+                    let prompt = format!("Enter the {}{optional}: ", in_sentence(spec.name));
+                    let value = if spec.kind == FieldKind::Hidden {
+                        prompt_password(prompt)?
+                    } else {
+                        get_input::<String>(&prompt).trim().to_string()
+                    };
+
+                    if !value.is_empty() || spec.required {
+                        fields.push(CustomField::new(spec.name.to_string(), spec.kind, value)?);
+                    }
+                }
+
                 Entry::new(name, location, username, password)
+                    .with_kind(kind)
+                    .with_fields(fields)
             };
 
-            if manager::entry_exists(&new.name, &file)? {
-                return Err(Error::EntryExists { name: new.name });
+            new.validate()?;
+            let mut vault = Vault::open(&file, &master_password(&file)?)?;
+            if kind.checks_strength() && !new.show_password().is_empty() {
+                warn_if_weak(out, &new);
             }
+            vault.add(new)?;
+            vault.save()?;
 
-            manager::add(new, file)?;
-
-            println!("Entry successfully added");
+            print!("{}", out.message("Entry successfully added"));
         }
         Action::Remove { name, file } => {
             let file = match file {
                 Some(path) => path,
                 None => config.file,
             };
-            if !manager::entry_exists(&name, &file)? {
-                return Err(Error::EntryDoesntExist { name });
-            }
 
-            manager::remove(&name, file)?;
-            println!("Entry `{name}` successfully removed");
+            let mut vault = Vault::open(&file, &master_password(&file)?)?;
+            let removed = vault.remove(&pick_entry(&vault, &name, prompt))?;
+            vault.save()?;
+
+            print!(
+                "{}",
+                out.message(&format!("Entry `{}` successfully removed", removed.name()))
+            );
         }
         Action::List { file } => {
             let file = match file {
                 Some(path) => path,
                 None => config.file,
             };
-            manager::list(file)?
+
+            let vault = Vault::open_read_only(&file, &master_password(&file)?)?;
+            print!("{}", out.entries(&vault.list()));
         }
-        Action::Edit { name, file } => {
+        Action::Edit {
+            name,
+            generate,
+            generator,
+            file,
+        } => {
+            let generated = match generate {
+                Some(length) => Some(generate_password(&config, length, generator)?),
+                None => None,
+            };
             let file = match file {
                 Some(path) => path,
                 None => config.file,
             };
-            if !manager::entry_exists(&name, &file)? {
-                return Err(Error::EntryDoesntExist { name });
-            }
+
+            let mut vault = Vault::open(&file, &master_password(&file)?)?;
+            let name = pick_entry(&vault, &name, prompt);
+            let kind = vault.get(&name)?.kind();
+
+            // Only ask about the parts of the entry its kind uses
+            let prompt = |label: Option<&str>| match label {
+                Some(label) => {
+                    get_input::<String>(&format!("Enter a new {}: ", in_sentence(label)))
+                        .trim()
+                        .to_string()
+                }
+                None => String::new(),
+            };
 
             let new_name = get_input::<String>("Enter a new name: ").trim().to_string();
-            let new_un = get_input::<String>("Enter a new username: ")
-                .trim()
-                .to_string();
-            let new_pw = prompt_password("Enter a new password: ")?
-                .trim()
-                .to_string();
-            let new_location = get_input::<String>("Enter a new location: ")
-                .trim()
-                .to_string();
-            let new_entry = Entry::new(new_name, new_location, new_un, new_pw);
-
-            manager::edit(&name, new_entry, file)?;
+            let new_un = prompt(kind.username_label());
+            let new_pw = match (generated, kind.password_label()) {
+                (Some(pw), _) => pw,
+                (None, Some(label)) => Zeroizing::new(prompt_password(format!(
+                    "Enter a new {}: ",
+                    in_sentence(label)
+                ))?),
+                (None, None) => Zeroizing::new(String::new()),
+            };
+            let new_location = prompt(kind.location_label());
+            let new_entry = Entry::new(new_name, new_location, new_un, new_pw.trim().to_string());
+            if kind.checks_strength() && !new_pw.trim().is_empty() {
+                warn_if_weak(out, &new_entry);
+            }
+
+            vault.edit(&name, new_entry)?;
+            vault.save()?;
         }
-        Action::Show { name, file, copy } => {
+        Action::Show {
+            name,
+            file,
+            copy,
+            reveal,
+        } => {
             let file = match file {
                 Some(path) => path,
-                None => config.file,
+                None => config.file.clone(),
             };
 
-            if !entry_exists(&name, &file)? {
-                return Err(Error::EntryDoesntExist { name });
+            let vault = Vault::open_read_only(&file, &master_password(&file)?)?;
+            let entry = vault.get(&pick_entry(&vault, &name, prompt))?;
+
+            let mode = if reveal {
+                PasswordMode::Revealed
+            } else {
+                PasswordMode::Masked
+            };
+
+            print!("{}", *Zeroizing::new(out.entry(entry, mode)));
+            if copy {
+                copy_secret(&config, &entry.show_password())?;
+            }
+        }
+        Action::Copy {
+            name,
+            field,
+            sequence,
+            file,
+        } => {
+            let file = match file {
+                Some(path) => path,
+                None => config.file.clone(),
+            };
+
+            if field == Field::Otp && !sequence {
+                copy_secret(&config, &next_otp(file, &name, prompt)?.code)?;
+                print!(
+                    "{}",
+                    out.message(&format!("Copied the {field} of `{name}`"))
+                );
+                return Ok(());
             }
 
-            manager::show(&name, file, copy)?;
+            let vault = Vault::open_read_only(&file, &master_password(&file)?)?;
+            let entry = vault.get(&pick_entry(&vault, &name, prompt))?;
+
+            if sequence {
+                copy_secret(&config, &entry.field(Field::Username)?)?;
+                get_input::<String>("Username copied. Press Enter to copy the password...");
+                copy_secret(&config, &entry.field(Field::Password)?)?;
+                print!("{}", out.message("Password copied"));
+            } else {
+                copy_secret(&config, &entry.field(field)?)?;
+                print!(
+                    "{}",
+                    out.message(&format!("Copied the {field} of `{name}`"))
+                );
+            }
+        }
+        Action::Otp {
+            name,
+            copy,
+            set,
+            counter,
+            remove,
+            file,
+        } => {
+            let file = match file {
+                Some(path) => path,
+                None => config.file.clone(),
+            };
+
+            if set.is_some() || remove {
+                let secret = match set {
+                    Some(Some(secret)) => Some(Zeroizing::new(secret)),
+                    Some(None) => Some(Zeroizing::new(prompt_password("Enter the secret: ")?)),
+                    None => None,
+                };
+                let otp = match (secret, counter) {
+                    (Some(secret), Some(counter)) => Some(Otp::parse_hotp(&secret, counter)?),
+                    (Some(secret), None) => Some(Otp::parse(&secret)?),
+                    (None, _) => None,
+                };
+
+                let mut vault = Vault::open(&file, &master_password(&file)?)?;
+                let name = pick_entry(&vault, &name, prompt);
+                vault.set_otp(&name, otp)?;
+                vault.save()?;
+
+                print!(
+                    "{}",
+                    out.message(if remove {
+                        "One-time password secret removed"
+                    } else {
+                        "One-time password secret saved"
+                    })
+                );
+                return Ok(());
+            }
+
+            let code = next_otp(file, &name, prompt)?;
+            if copy {
+                copy_secret(&config, &code.code)?;
+                let mut message = format!("Copied the one-time password for `{name}`");
+                if let Some(expires_in) = code.expires_in {
+                    message.push_str(&format!(", which expires in {expires_in} seconds"));
+                }
+                print!("{}", out.message(&message));
+            } else {
+                print!("{}", *Zeroizing::new(out.otp(&code)));
+            }
+        }
+        Action::Audit {
+            max_age,
+            threshold,
+            breaches,
+            file,
+        } => {
+            let file = match file {
+                Some(path) => path,
+                None => config.file,
+            };
+
+            let vault = Vault::open_read_only(&file, &master_password(&file)?)?;
+            let report = audit::audit(
+                vault.entries(),
+                &AuditOptions {
+                    max_age_days: max_age,
+                    breaches,
+                },
+            )?;
+            print!("{}", out.audit(&report));
+
+            if report.total() > threshold {
+                return Err(Error::AuditFailed {
+                    findings: report.total(),
+                    threshold,
+                }
+                .into());
+            }
         }
         Action::Notes { subcmd } => match subcmd {
             NotesSubcmd::Add { note, entry, file } => {
@@ -121,12 +381,12 @@ fn main() -> Result<()> {
                     None => config.file,
                 };
 
-                if !entry_exists(&entry, &file)? {
-                    return Err(Error::EntryDoesntExist { name: entry });
-                }
+                let mut vault = Vault::open(&file, &master_password(&file)?)?;
+                let entry = pick_entry(&vault, &entry, prompt);
+                vault.add_note(&entry, note)?;
+                vault.save()?;
 
-                notes::add(&entry, note, file)?;
-                println!("Note successfully added");
+                print!("{}", out.message("Note successfully added"));
             }
             NotesSubcmd::Remove { entry, id, file } => {
                 let file = match file {
@@ -134,12 +394,12 @@ fn main() -> Result<()> {
                     None => config.file,
                 };
 
-                if !entry_exists(&entry, &file)? {
-                    return Err(Error::EntryDoesntExist { name: entry });
-                }
+                let mut vault = Vault::open(&file, &master_password(&file)?)?;
+                let entry = pick_entry(&vault, &entry, prompt);
+                vault.remove_note(&entry, id)?;
+                vault.save()?;
 
-                notes::remove(&entry, id, file)?;
-                println!("Note successfully edited");
+                print!("{}", out.message("Note successfully removed"));
             }
             NotesSubcmd::Edit {
                 entry,
@@ -151,12 +411,13 @@ fn main() -> Result<()> {
                     Some(path) => path,
                     None => config.file,
                 };
-                if !entry_exists(&entry, &file)? {
-                    return Err(Error::EntryDoesntExist { name: entry });
-                }
 
-                notes::edit(&entry, id, new_note, file)?;
-                println!("Note successfully edited");
+                let mut vault = Vault::open(&file, &master_password(&file)?)?;
+                let entry = pick_entry(&vault, &entry, prompt);
+                vault.edit_note(&entry, id, new_note)?;
+                vault.save()?;
+
+                print!("{}", out.message("Note successfully edited"));
             }
             NotesSubcmd::List { entry, file } => {
                 let file = match file {
@@ -164,11 +425,92 @@ fn main() -> Result<()> {
                     None => config.file,
                 };
 
-                if !entry_exists(&entry, &file)? {
-                    return Err(Error::EntryDoesntExist { name: entry });
-                }
+                let vault = Vault::open_read_only(&file, &master_password(&file)?)?;
+                let entry = vault.get(&pick_entry(&vault, &entry, prompt))?;
+                print!("{}", out.notes(entry));
+            }
+        },
+        Action::Field { subcmd } => match subcmd {
+            FieldSubcmd::Add {
+                entry,
+                name,
+                value,
+                kind,
+                file,
+            } => {
+                let file = match file {
+                    Some(path) => path,
+                    None => config.file,
+                };
+
+                let value = match value {
+                    Some(value) => value,
+                    None => prompt_password("Enter the value: ")?,
+                };
+                let field = CustomField::new(name, kind, value)?;
+
+                let mut vault = Vault::open(&file, &master_password(&file)?)?;
+                let entry = pick_entry(&vault, &entry, prompt);
+                vault.add_field(&entry, field)?;
+                vault.save()?;
+
+                print!("{}", out.message("Field successfully added"));
+            }
+            FieldSubcmd::Set {
+                entry,
+                name,
+                value,
+                file,
+            } => {
+                let file = match file {
+                    Some(path) => path,
+                    None => config.file,
+                };
+
+                let value = match value {
+                    Some(value) => value,
+                    None => prompt_password("Enter the new value: ")?,
+                };
+
+                let mut vault = Vault::open(&file, &master_password(&file)?)?;
+                let entry = pick_entry(&vault, &entry, prompt);
+                vault.set_field(&entry, &name, value)?;
+                vault.save()?;
+
+                print!("{}", out.message("Field successfully changed"));
+            }
+            FieldSubcmd::Remove { entry, name, file } => {
+                let file = match file {
+                    Some(path) => path,
+                    None => config.file,
+                };
+
+                let mut vault = Vault::open(&file, &master_password(&file)?)?;
+                let entry = pick_entry(&vault, &entry, prompt);
+                vault.remove_field(&entry, &name)?;
+                vault.save()?;
 
-                notes::list(&entry, file)?;
+                print!("{}", out.message("Field successfully removed"));
+            }
+            FieldSubcmd::List {
+                entry,
+                reveal,
+                file,
+            } => {
+                let file = match file {
+                    Some(path) => path,
+                    None => config.file,
+                };
+
+                let mode = if reveal {
+                    PasswordMode::Revealed
+                } else {
+                    PasswordMode::Masked
+                };
+
+                let vault = Vault::open_read_only(&file, &master_password(&file)?)?;
+                let entry = vault.get(&pick_entry(&vault, &entry, prompt))?;
+                print!("{}", *Zeroizing::new(out.fields(entry, mode)));
             }
         },
         Action::Config { option } => match option {
@@ -176,12 +518,194 @@ fn main() -> Result<()> {
                 config.file = path;
                 confy::store("PassMan", config)?;
             }
+            ConfigField::ClipboardTimeout { seconds } => {
+                config.clipboard_timeout = seconds;
+                confy::store("PassMan", config)?;
+            }
+            ConfigField::ClipboardBackend { backend } => {
+                config.clipboard_backend = backend;
+                confy::store("PassMan", config)?;
+            }
+            ConfigField::Profile {
+                name,
+                length,
+                policy: policy_args,
+            } => {
+                let mut policy = config.profiles.remove(&name).unwrap_or_default();
+                if let Some(length) = length {
+                    policy.length = length;
+                }
+                policy_args.apply(&mut policy);
+                policy.validate()?;
+
+                config.profiles.insert(name, policy);
+                confy::store("PassMan", config)?;
+            }
+            ConfigField::RemoveProfile { name } => {
+                if config.profiles.remove(&name).is_none() {
+                    return Err(Error::ProfileDoesntExist { name }.into());
+                }
+                confy::store("PassMan", config)?;
+            }
         },
+        Action::ClearClipboard { after, backend } => clipboard::run_clear_helper(backend, after)?,
     }
 
     Ok(())
 }
 
+/// Generate a password, or a passphrase if one was asked for.
+///
+/// Passwords start from the default options or the given profile,
+/// with `length` & any other options given overriding them
+fn generate_password(
+    config: &Config,
+    length: Option<u32>,
+    args: GeneratorArgs,
+) -> Result<Zeroizing<String>> {
+    if let Some(policy) = args.passphrase.policy() {
+        return Ok(Zeroizing::new(generator::generate_passphrase(&policy)?));
+    }
+
+    let mut policy = match args.profile {
+        Some(name) => config
+            .profiles
+            .get(&name)
+            .cloned()
+            .ok_or(Error::ProfileDoesntExist { name })?,
+        None => GeneratorPolicy::default(),
+    };
+    if let Some(length) = length {
+        policy.length = length;
+    }
+    args.policy.apply(&mut policy);
+
+    Ok(Zeroizing::new(generator::generate(&policy)?))
+}
+
+/// Get the one-time password to use now for the entry `name` refers to,
+/// saving the vault first if an HOTP counter was moved on
+fn next_otp(file: PathBuf, name: &str, prompt: bool) -> Result<OtpCode> {
+    let mut vault = Vault::open(&file, &master_password(&file)?)?;
+    let name = &pick_entry(&vault, name, prompt);
+    let counter_based = matches!(
+        vault.get(name)?.otp().map(Otp::kind),
+        Some(OtpKind::Hotp { .. })
+    );
+
+    let code = vault.next_otp(name)?;
+    if counter_based {
+        vault.save()?;
+    }
+
+    Ok(code)
+}
+
+/// Ask which entry was meant if `reference` could mean more than one,
+/// returning the ID of the one picked.
+///
+/// Otherwise, if `prompt` is `false` or nothing is picked before stdin is closed,
+/// `reference` is returned as it is, leaving the vault to report it as ambiguous
+fn pick_entry(vault: &Vault, reference: &str, prompt: bool) -> String {
+    let matches = vault.matches(reference);
+    if matches.len() < 2 || !prompt {
+        return reference.to_string();
+    }
+
+    println!("`{reference}` could mean any of:");
+    for (idx, entry) in matches.iter().enumerate() {
+        println!(
+            "{}: {} [{}] ({})",
+            idx + 1,
+            entry.reference(),
+            entry.location(),
+            entry.id()
+        );
+    }
+
+    loop {
+        let choice = get_input::<String>("Which one? ");
+        // Nothing at all, not even a newline, means stdin was closed
+        if choice.is_empty() {
+            println!();
+            return reference.to_string();
+        }
+
+        let picked = choice
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .and_then(|choice| matches.get(choice.checked_sub(1)?));
+        if let Some(entry) = picked {
+            return entry.id().to_string();
+        }
+    }
+}
+
+/// Copy `secret` to the clipboard,
+/// clearing it again after the configured timeout
+fn copy_secret(config: &Config, secret: &str) -> Result<()> {
+    clipboard::copy(config.clipboard_backend, secret)?;
+    if config.clipboard_timeout > 0 {
+        clipboard::clear_after(config.clipboard_backend, secret, config.clipboard_timeout)?;
+    }
+
+    Ok(())
+}
+
+/// Print a warning if `entry`'s password is easy to guess
+fn warn_if_weak(out: &dyn Renderer, entry: &Entry) {
+    let strength = entry.strength();
+    if !strength.is_weak() {
+        return;
+    }
+
+    let mut warning = format!(
+        "This password is {} ({} bits)",
+        strength.label, strength.entropy_bits
+    );
+    if let Some(reason) = &strength.warning {
+        warning.push_str(&format!(". {reason}"));
+    }
+    eprint!("{}", out.warning(&warning));
+}
+
+/// Get the master password for the vault at `file`, from `PASSMAN_MASTER_PASSWORD` or a prompt.
+///
+/// If the vault hasn't been encrypted yet, it's about to be encrypted with this password,
+/// so it has to be entered again, or given in `PASSMAN_MASTER_PASSWORD_CONFIRM`, to catch typos
+fn master_password(file: &Path) -> Result<Zeroizing<String>> {
+    let password = match std::env::var("PASSMAN_MASTER_PASSWORD") {
+        Ok(pw) => Zeroizing::new(pw),
+        Err(_) => Zeroizing::new(prompt_password("Enter master password: ")?),
+    };
+    if Vault::is_encrypted(file)? {
+        return Ok(password);
+    }
+
+    let confirmation = match std::env::var("PASSMAN_MASTER_PASSWORD_CONFIRM") {
+        Ok(pw) => Zeroizing::new(pw),
+        Err(_) => Zeroizing::new(prompt_password(
+            "The vault will be encrypted with this password. Enter it again to confirm: ",
+        )?),
+    };
+    if *confirmation != *password {
+        return Err(Error::PasswordMismatch.into());
+    }
+
+    Ok(password)
+}
+
+/// A label like `Username` as it appears mid-sentence,
+/// leaving acronyms like `PIN` & `SSID` alone
+fn in_sentence(label: &str) -> String {
+    if label.chars().all(|c| !c.is_lowercase()) {
+        label.to_string()
+    } else {
+        label.to_lowercase()
+    }
+}
+
 fn get_input<T>(prompt: &str) -> T
 where
     T: std::str::FromStr,
@@ -1,83 +1,383 @@
 use core::fmt;
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use zeroize::Zeroizing;
 
-/// Struct to serialise & deserialise JSON to & from
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+use crate::{
+    error::{Error, Result},
+    manager::{fields::CustomField, format::timestamp, kinds::EntryKind},
+    otp::Otp,
+    strength::{self, Strength},
+};
+
+/// A single saved password, along with
+/// the details needed to use it
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Entry {
+    /// Identifies the entry even if it's renamed.
+    /// Entries from before there were IDs are given one when the vault is upgraded
+    #[serde(default = "Uuid::new_v4")]
+    pub(crate) id: Uuid,
+    /// Entries from before there were kinds are all logins
+    #[serde(default)]
+    pub(crate) kind: EntryKind,
     pub(crate) name: String,
     pub(crate) username: String,
-    pub(crate) password: Vec<u8>,
+    pub(crate) password: Zeroizing<Vec<u8>>,
     pub(crate) location: String,
     pub(crate) notes: Vec<String>,
+    /// Any extra information, like PINs or security questions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) fields: Vec<CustomField>,
+    /// The secret for generating two-factor authentication codes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) otp: Option<Otp>,
+    /// Unix timestamp of when the entry was added.
+    /// Missing for entries added before timestamps were recorded
+    #[serde(default)]
+    pub(crate) created: Option<u64>,
+    /// Unix timestamp of when the password was last changed
+    #[serde(default)]
+    pub(crate) password_changed: Option<u64>,
 }
 
 impl Entry {
+    /// Create a new entry with no notes.
+    ///
+    /// `password` is always stored exactly as given.
+    /// Use the [`generator`](crate::generator) module to create a random one
     pub fn new(name: String, location: String, username: String, password: String) -> Self {
-        let password = Self::hide_password(if password.starts_with("!gen") {
-            let length = password
-                .chars()
-                .skip(4)
-                .filter(|c| c.is_ascii_digit())
-                .collect::<String>()
-                .parse()
-                .unwrap();
-            crate::generator::generate_pw(
-                length,
-                !password.contains("-n"),
-                !password.contains("-s"),
-            )
-        } else {
-            password
-        });
+        let password = Self::hide_password(password);
 
         Self {
+            id: Uuid::new_v4(),
+            kind: EntryKind::Login,
             name,
             username,
             password,
             location,
             notes: Vec::new(),
+            fields: Vec::new(),
+            otp: None,
+            created: Some(timestamp()),
+            password_changed: Some(timestamp()),
+        }
+    }
+
+    /// Make this entry a different kind, like a card or Wi-Fi network
+    pub fn with_kind(mut self, kind: EntryKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Give this entry custom fields, replacing any it already has
+    pub fn with_fields(mut self, fields: Vec<CustomField>) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Check the entry has every field its kind needs, all valid,
+    /// & no two custom fields with the same name
+    pub fn validate(&self) -> Result<()> {
+        for (idx, field) in self.fields.iter().enumerate() {
+            if self.fields[..idx]
+                .iter()
+                .any(|other| other.name.eq_ignore_ascii_case(&field.name))
+            {
+                return Err(Error::CustomFieldExists {
+                    entry: self.name.clone(),
+                    field: field.name.clone(),
+                });
+            }
         }
+
+        self.kind.validate(self)
+    }
+
+    fn hide_password(password: String) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(password.into_bytes())
+    }
+
+    /// The password in plain text, wiped from memory when dropped
+    pub fn show_password(&self) -> Zeroizing<String> {
+        Zeroizing::new(String::from_utf8(self.password.to_vec()).unwrap())
+    }
+
+    /// The entry's unique ID, which never changes
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// What the entry holds, e.g. a login or a card
+    pub fn kind(&self) -> EntryKind {
+        self.kind
+    }
+
+    /// The name the entry is usually looked up by.
+    /// Several entries can have the same name, as long as their usernames differ
+    pub fn name(&self) -> &str {
+        &self.name
     }
 
-    fn hide_password(password: String) -> Vec<u8> {
-        Vec::from(password.as_bytes())
+    /// `<name>:<username>`, which picks out this entry
+    /// even if others have the same name
+    pub fn reference(&self) -> String {
+        format!("{}:{}", self.name, self.username)
     }
 
-    pub fn show_password(&self) -> String {
-        String::from_utf8(self.password.clone()).unwrap()
+    /// Returns `true` if the entry's name is `name`, ignoring case
+    pub(crate) fn is_called(&self, name: &str) -> bool {
+        self.name.to_lowercase() == name.to_lowercase()
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// Where the password is used, e.g. a website URL
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    pub fn notes(&self) -> &[String] {
+        &self.notes
+    }
+
+    /// The custom fields, in the order they were added
+    pub fn fields(&self) -> &[CustomField] {
+        &self.fields
+    }
+
+    /// The custom field called `name`, ignoring case
+    pub fn custom_field(&self, name: &str) -> Option<&CustomField> {
+        self.fields
+            .iter()
+            .find(|field| field.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The settings for generating two-factor authentication codes, if any
+    pub fn otp(&self) -> Option<&Otp> {
+        self.otp.as_ref()
+    }
+
+    /// Unix timestamp of when the entry was added,
+    /// or `None` if it was added before PassMan recorded this
+    pub fn created(&self) -> Option<u64> {
+        self.created
+    }
+
+    /// Unix timestamp of when the password was last changed,
+    /// or `None` if it hasn't been since PassMan started recording this
+    pub fn password_changed(&self) -> Option<u64> {
+        self.password_changed
+    }
+
+    /// How hard the password would be to guess,
+    /// taking into account how similar it is to the rest of the entry
+    pub fn strength(&self) -> Strength {
+        strength::estimate(
+            &self.show_password(),
+            &[&self.name, &self.username, &self.location],
+        )
+    }
+
+    /// The details of the entry that are safe to show in a list
+    pub fn summary(&self) -> EntrySummary {
+        EntrySummary {
+            id: self.id,
+            kind: self.kind,
+            name: self.name.clone(),
+            username: self.username.clone(),
+            location: self.location.clone(),
+            notes: self.notes.len(),
+        }
     }
 }
 
-impl fmt::Display for Entry {
+/// A single piece of information stored in an [`Entry`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Field {
+    Username,
+    #[default]
+    Password,
+    Location,
+    /// The current one-time password.
+    /// For HOTP, this is the next code, without moving the counter on
+    Otp,
+    /// The note with the given id
+    Note(usize),
+}
+
+impl Entry {
+    /// Get the value of a single field.
+    /// Returned in a [`Zeroizing`] since the field may be the password
+    pub fn field(&self, field: Field) -> Result<Zeroizing<String>> {
+        Ok(match field {
+            Field::Username => Zeroizing::new(self.username.clone()),
+            Field::Password => self.show_password(),
+            Field::Location => Zeroizing::new(self.location.clone()),
+            Field::Otp => match &self.otp {
+                Some(otp) => otp.current().code,
+                None => {
+                    return Err(Error::NoOtp {
+                        name: self.name.clone(),
+                    })
+                }
+            },
+            Field::Note(id) => match self.notes.get(id) {
+                Some(note) => Zeroizing::new(note.clone()),
+                None => {
+                    return Err(Error::NoteIdOOB {
+                        id,
+                        len: self.notes.len(),
+                    })
+                }
+            },
+        })
+    }
+}
+
+impl FromStr for Field {
+    type Err = Error;
+
+    /// Parses `username`, `password`, `location`, `otp` or `note:<id>`
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidField {
+            field: s.to_string(),
+        };
+
+        match s.to_lowercase().as_str() {
+            "username" => Ok(Field::Username),
+            "password" => Ok(Field::Password),
+            "location" => Ok(Field::Location),
+            "otp" => Ok(Field::Otp),
+            other => {
+                let id = other.strip_prefix("note:").ok_or_else(invalid)?;
+                id.parse().map(Field::Note).map_err(|_| invalid())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Field {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "{} [for {}]", self.name, self.location)?;
-        writeln!(f, "   Username: {}", self.username)?;
-        writeln!(f, "   Password: {}", self.show_password())?;
-        write!(f, "   Contains {} notes", self.notes.len())
+        match self {
+            Field::Username => write!(f, "username"),
+            Field::Password => write!(f, "password"),
+            Field::Location => write!(f, "location"),
+            Field::Otp => write!(f, "one-time password"),
+            Field::Note(id) => write!(f, "note {id}"),
+        }
     }
 }
 
-impl PartialEq<str> for Entry {
-    /// Purely so I dont accidentally forget to
-    /// convert both to lowercase
-    fn eq(&self, other: &str) -> bool {
-        self.name.to_lowercase() == other.to_lowercase()
+/// An overview of an [`Entry`], without the password or notes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntrySummary {
+    pub id: Uuid,
+    pub kind: EntryKind,
+    pub name: String,
+    pub username: String,
+    pub location: String,
+    /// The number of notes the entry has
+    pub notes: usize,
+}
+
+impl EntrySummary {
+    /// `<name>:<username>`, like [`Entry::reference`]
+    pub fn reference(&self) -> String {
+        format!("{}:{}", self.name, self.username)
     }
 }
 
-impl PartialEq<&str> for Entry {
-    /// Purely so I dont accidentally forget to
-    /// convert both to lowercase
-    fn eq(&self, other: &&str) -> bool {
-        self.name.to_lowercase() == other.to_lowercase()
+impl fmt::Debug for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Entry")
+            .field("kind", &self.kind)
+            .field("name", &self.name)
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .field("location", &self.location)
+            .field("notes", &self.notes)
+            .field("fields", &self.fields)
+            .field("otp", &self.otp)
+            .field("created", &self.created)
+            .field("password_changed", &self.password_changed)
+            .finish()
     }
 }
 
-impl PartialEq<String> for Entry {
-    /// Purely so I dont accidentally forget to
-    /// convert both to lowercase
-    fn eq(&self, other: &String) -> bool {
-        self.name.to_lowercase() == other.to_lowercase()
+/// What to show in place of a hidden password.
+/// Always the same length, so it doesn't give away the real one's
+pub const PASSWORD_MASK: &str = "********";
+
+/// Whether to show an entry's password when displaying it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PasswordMode {
+    /// Replace the password with [`PASSWORD_MASK`]
+    #[default]
+    Masked,
+    /// Show the password in plain text
+    Revealed,
+}
+
+/// Displays an [`Entry`] with its password shown according to a [`PasswordMode`].
+/// Created by [`Entry::display`]
+pub struct EntryDisplay<'a> {
+    entry: &'a Entry,
+    mode: PasswordMode,
+}
+
+impl Entry {
+    /// Display the entry, only showing the password
+    /// if `mode` is [`PasswordMode::Revealed`]
+    pub fn display(&self, mode: PasswordMode) -> EntryDisplay<'_> {
+        EntryDisplay { entry: self, mode }
+    }
+}
+
+impl fmt::Display for EntryDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entry = self.entry;
+        let kind = entry.kind;
+
+        // Logins are shown the way every entry used to be
+        if kind == EntryKind::Login {
+            writeln!(f, "{} [for {}]", entry.name, entry.location)?;
+        } else {
+            writeln!(f, "{} ({})", entry.name, kind.label())?;
+            if let Some(label) = kind.location_label() {
+                writeln!(f, "   {label}: {}", entry.location)?;
+            }
+        }
+        if let Some(label) = kind.username_label() {
+            writeln!(f, "   {label}: {}", entry.username)?;
+        }
+        if let Some(label) = kind.password_label() {
+            match self.mode {
+                PasswordMode::Masked => writeln!(f, "   {label}: {PASSWORD_MASK}")?,
+                PasswordMode::Revealed => writeln!(f, "   {label}: {}", *entry.show_password())?,
+            }
+        }
+        for field in &entry.fields {
+            match self.mode {
+                PasswordMode::Masked if field.is_hidden() => {
+                    writeln!(f, "   {}: {PASSWORD_MASK}", field.name)?
+                }
+                _ => writeln!(f, "   {}: {}", field.name, field.value())?,
+            }
+        }
+        writeln!(f, "   Contains {} notes", entry.notes.len())?;
+        write!(f, "   ID: {}", entry.id)
+    }
+}
+
+/// Displays the entry with its password masked.
+/// Use [`Entry::display`] to show the password
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display(PasswordMode::Masked).fmt(f)
     }
 }
@@ -0,0 +1,237 @@
+use core::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+use zeroize::Zeroizing;
+
+use crate::{
+    error::{Error, Result},
+    manager::Vault,
+};
+
+/// What kind of value a [`CustomField`] holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldKind {
+    #[default]
+    Text,
+    /// A secret, like a PIN, which is masked like the password
+    Hidden,
+    Url,
+    Email,
+    Number,
+    /// A date in the form `YYYY-MM-DD`
+    Date,
+}
+
+impl FieldKind {
+    /// Check that `value` is valid for this kind of field
+    pub fn validate(self, value: &str) -> Result<()> {
+        let valid = match self {
+            FieldKind::Text | FieldKind::Hidden => true,
+            FieldKind::Url => Url::parse(value).is_ok(),
+            FieldKind::Email => value.split_once('@').is_some_and(|(user, domain)| {
+                !user.is_empty() && domain.contains('.') && !domain.contains('@')
+            }),
+            FieldKind::Number => value.trim().parse::<f64>().is_ok(),
+            FieldKind::Date => is_date(value),
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::InvalidFieldValue {
+                kind: self,
+                value: value.to_string(),
+            })
+        }
+    }
+}
+
+/// Returns `true` if `value` is a real date in the form `YYYY-MM-DD`
+fn is_date(value: &str) -> bool {
+    let parts: Vec<&str> = value.split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return false;
+    };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return false;
+    }
+    let (Ok(year), Ok(month), Ok(day)) = (year.parse::<u32>(), month.parse(), day.parse()) else {
+        return false;
+    };
+
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+
+    (1..=days).contains(&day)
+}
+
+impl FromStr for FieldKind {
+    type Err = Error;
+
+    /// Parses `text`, `hidden` (or `secret`), `url`, `email`, `number` or `date`
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(FieldKind::Text),
+            "hidden" | "secret" => Ok(FieldKind::Hidden),
+            "url" => Ok(FieldKind::Url),
+            "email" => Ok(FieldKind::Email),
+            "number" => Ok(FieldKind::Number),
+            "date" => Ok(FieldKind::Date),
+            _ => Err(Error::InvalidFieldKind {
+                kind: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for FieldKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldKind::Text => write!(f, "text"),
+            FieldKind::Hidden => write!(f, "hidden"),
+            FieldKind::Url => write!(f, "url"),
+            FieldKind::Email => write!(f, "email"),
+            FieldKind::Number => write!(f, "number"),
+            FieldKind::Date => write!(f, "date"),
+        }
+    }
+}
+
+/// An extra, named piece of information stored in an entry,
+/// like a PIN or the answer to a security question
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomField {
+    pub(crate) name: String,
+    pub(crate) kind: FieldKind,
+    pub(crate) value: Zeroizing<String>,
+}
+
+impl CustomField {
+    /// Create a field, failing if `value` isn't valid for `kind`
+    pub fn new(name: String, kind: FieldKind, value: String) -> Result<Self> {
+        let value = Zeroizing::new(value);
+        kind.validate(&value)?;
+
+        Ok(Self { name, kind, value })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> FieldKind {
+        self.kind
+    }
+
+    /// The value in plain text, even for hidden fields
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Returns `true` if the value should be masked like a password
+    pub fn is_hidden(&self) -> bool {
+        self.kind == FieldKind::Hidden
+    }
+}
+
+impl fmt::Debug for CustomField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value: &dyn fmt::Debug = if self.is_hidden() {
+            &"<redacted>"
+        } else {
+            &*self.value
+        };
+
+        f.debug_struct("CustomField")
+            .field("name", &self.name)
+            .field("kind", &self.kind)
+            .field("value", value)
+            .finish()
+    }
+}
+
+impl Vault {
+    /// The custom fields of the entry called `entry_name`
+    pub fn fields(&self, entry_name: &str) -> Result<&[CustomField]> {
+        Ok(&self.get(entry_name)?.fields)
+    }
+
+    /// Add a custom field to the entry called `entry_name`,
+    /// failing if it already has a field with the same name
+    pub fn add_field(&mut self, entry_name: &str, field: CustomField) -> Result<()> {
+        let entry = self.get_mut(entry_name)?;
+        if entry.custom_field(&field.name).is_some() {
+            return Err(Error::CustomFieldExists {
+                entry: entry_name.to_string(),
+                field: field.name,
+            });
+        }
+
+        entry.fields.push(field);
+        if let Err(e) = entry.kind.validate(entry) {
+            entry.fields.pop();
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Change the value of the custom field called `field_name`, keeping its kind
+    pub fn set_field(&mut self, entry_name: &str, field_name: &str, value: String) -> Result<()> {
+        let spec = self.get(entry_name)?.kind.field_spec(field_name);
+        let field = self.custom_field_mut(entry_name, field_name)?;
+        let value = Zeroizing::new(value);
+        field.kind.validate(&value)?;
+        if let Some(spec) = spec {
+            spec.validate(&value)?;
+        }
+        field.value = value;
+
+        Ok(())
+    }
+
+    /// Remove the custom field called `field_name`, returning it.
+    /// Fields the entry's kind needs can't be removed
+    pub fn remove_field(&mut self, entry_name: &str, field_name: &str) -> Result<CustomField> {
+        let entry = self.get_mut(entry_name)?;
+        if let Some(spec) = entry.kind.field_spec(field_name) {
+            if spec.required {
+                return Err(Error::MissingField {
+                    kind: entry.kind.label().to_string(),
+                    field: spec.name.to_string(),
+                });
+            }
+        }
+
+        let idx = entry
+            .fields
+            .iter()
+            .position(|field| field.name.eq_ignore_ascii_case(field_name))
+            .ok_or_else(|| Error::CustomFieldDoesntExist {
+                entry: entry_name.to_string(),
+                field: field_name.to_string(),
+            })?;
+
+        Ok(entry.fields.remove(idx))
+    }
+
+    fn custom_field_mut(&mut self, entry_name: &str, field_name: &str) -> Result<&mut CustomField> {
+        self.get_mut(entry_name)?
+            .fields
+            .iter_mut()
+            .find(|field| field.name.eq_ignore_ascii_case(field_name))
+            .ok_or_else(|| Error::CustomFieldDoesntExist {
+                entry: entry_name.to_string(),
+                field: field_name.to_string(),
+            })
+    }
+}
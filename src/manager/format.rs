@@ -0,0 +1,204 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+use super::entry::Entry;
+
+/// The version of the vault document written by this version of PassMan
+///
+/// Versions before this one stored a bare JSON array of entries,
+/// which is treated as version 0. Version 2 gave every entry an ID
+pub const FORMAT_VERSION: u32 = 2;
+
+/// The top-level document stored in the vault file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultDocument {
+    pub(crate) version: u32,
+    pub(crate) metadata: Metadata,
+    pub(crate) entries: Vec<Entry>,
+}
+
+/// Information about the vault itself, rather than its entries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Metadata {
+    /// Unix timestamp of when the vault was created
+    /// (or migrated from a legacy vault)
+    pub(crate) created: u64,
+    /// Unix timestamp of the last time the vault was written
+    pub(crate) modified: u64,
+}
+
+impl VaultDocument {
+    pub fn new(entries: Vec<Entry>) -> Self {
+        let now = timestamp();
+
+        Self {
+            version: FORMAT_VERSION,
+            metadata: Metadata {
+                created: now,
+                modified: now,
+            },
+            entries,
+        }
+    }
+
+    /// Mark the document as modified just now
+    pub fn touch(&mut self) {
+        self.metadata.modified = timestamp();
+    }
+}
+
+/// The result of reading a vault that may be in an older format
+pub struct Migrated {
+    pub document: VaultDocument,
+    /// The format version the document was stored in,
+    /// if it wasn't [`FORMAT_VERSION`]
+    pub from_version: Option<u32>,
+}
+
+/// Just enough of a vault document to tell which format it's in
+#[derive(Deserialize)]
+struct VersionOnly {
+    version: Option<u32>,
+}
+
+/// Parse a decrypted (or legacy plaintext) vault,
+/// upgrading it to the current format if necessary.
+///
+/// The entries are deserialised straight from `data`, rather than through
+/// an intermediate [`serde_json::Value`] that would leave the passwords in memory
+pub fn migrate(data: &[u8]) -> Result<Migrated> {
+    let legacy = data
+        .iter()
+        .find(|b| !b.is_ascii_whitespace())
+        .is_some_and(|&b| b == b'[');
+    if legacy {
+        return Ok(Migrated {
+            document: VaultDocument::new(serde_json::from_slice(data)?),
+            from_version: Some(0),
+        });
+    }
+
+    let version = serde_json::from_slice::<VersionOnly>(data)
+        .map_err(|_| Error::CorruptVault)?
+        .version
+        .ok_or(Error::CorruptVault)?;
+
+    match version {
+        FORMAT_VERSION => Ok(Migrated {
+            document: serde_json::from_slice(data)?,
+            from_version: None,
+        }),
+        // Entries are given an ID as they're read, which then needs saving
+        1 => {
+            let mut document: VaultDocument = serde_json::from_slice(data)?;
+            document.version = FORMAT_VERSION;
+
+            Ok(Migrated {
+                document,
+                from_version: Some(version),
+            })
+        }
+        _ => Err(Error::UnsupportedVersion { version }),
+    }
+}
+
+/// The current Unix timestamp, in seconds
+pub(crate) fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto;
+
+    const MASTER_PW: &str = "correct horse battery staple";
+
+    /// Encrypt `plaintext` as it would be on disk, then decrypt & migrate it
+    fn round_trip(plaintext: &[u8]) -> Migrated {
+        let encrypted = crypto::encrypt(plaintext, MASTER_PW).unwrap();
+        migrate(&crypto::decrypt(&encrypted, MASTER_PW).unwrap()).unwrap()
+    }
+
+    fn assert_github(entry: &Entry) {
+        assert_eq!(entry.name(), "GitHub");
+        assert_eq!(entry.username(), "octocat");
+        assert_eq!(*entry.show_password(), "hunter2");
+        assert_eq!(entry.location(), "github.com");
+        assert_eq!(entry.notes(), ["2FA enabled"]);
+    }
+
+    #[test]
+    fn migrates_legacy_array() {
+        let legacy = br#"[{"name":"GitHub","username":"octocat","password":[104,117,110,116,101,114,50],"location":"github.com","notes":["2FA enabled"]}]"#;
+
+        let Migrated {
+            document,
+            from_version,
+        } = round_trip(legacy);
+        assert_eq!(from_version, Some(0));
+        assert_eq!(document.version, FORMAT_VERSION);
+        assert_eq!(document.entries.len(), 1);
+        assert_github(&document.entries[0]);
+        assert_eq!(document.entries[0].created(), None);
+    }
+
+    #[test]
+    fn migrates_v1_document() {
+        let v1 = br#"{"version":1,"metadata":{"created":100,"modified":200},"entries":[
+            {"name":"GitHub","username":"octocat","password":[104,117,110,116,101,114,50],"location":"github.com","notes":["2FA enabled"]},
+            {"name":"GitHub","username":"hubot","password":[120],"location":"github.com","notes":[]}
+        ]}"#;
+
+        let Migrated {
+            document,
+            from_version,
+        } = round_trip(v1);
+        assert_eq!(from_version, Some(1));
+        assert_eq!(document.version, FORMAT_VERSION);
+        assert_eq!(document.metadata.created, 100);
+        assert_github(&document.entries[0]);
+        assert_ne!(document.entries[0].id(), document.entries[1].id());
+    }
+
+    #[test]
+    fn round_trips_current_document() {
+        let document = VaultDocument::new(vec![Entry::new(
+            "GitHub".to_string(),
+            "github.com".to_string(),
+            "octocat".to_string(),
+            "hunter2".to_string(),
+        )]);
+        let plaintext = serde_json::to_vec(&document).unwrap();
+
+        let migrated = round_trip(&plaintext);
+        assert_eq!(migrated.from_version, None);
+        assert_eq!(migrated.document.version, FORMAT_VERSION);
+        assert_eq!(migrated.document.entries, document.entries);
+    }
+
+    #[test]
+    fn upgraded_ids_survive_saving() {
+        let legacy = br#"[{"name":"GitHub","username":"octocat","password":[104],"location":"","notes":[]}]"#;
+        let upgraded = round_trip(legacy).document;
+
+        let saved = round_trip(&serde_json::to_vec(&upgraded).unwrap());
+        assert_eq!(saved.from_version, None);
+        assert_eq!(saved.document.entries[0].id(), upgraded.entries[0].id());
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let future = br#"{"version":99,"metadata":{"created":0,"modified":0},"entries":[]}"#;
+        assert!(matches!(
+            migrate(future),
+            Err(Error::UnsupportedVersion { version: 99 })
+        ));
+        assert!(matches!(migrate(b"{}"), Err(Error::CorruptVault)));
+    }
+}
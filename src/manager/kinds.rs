@@ -0,0 +1,276 @@
+use core::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, Result},
+    manager::{entry::Entry, fields::FieldKind},
+};
+
+/// What an entry holds, which decides how its fields are labelled
+/// & which custom fields it needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EntryKind {
+    /// A username & password for a website or app
+    #[default]
+    Login,
+    /// Free text, kept in the entry's notes
+    #[serde(rename = "note")]
+    SecureNote,
+    Card,
+    SshKey,
+    ApiToken,
+    Wifi,
+    Database,
+}
+
+/// An extra check on a field's value, returning `true` if it's valid
+type Check = fn(&str) -> bool;
+
+/// A custom field that an [`EntryKind`] has
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldSpec {
+    pub name: &'static str,
+    pub kind: FieldKind,
+    /// Whether entries of this kind must have the field
+    pub required: bool,
+    /// An extra check on the value, with what's wrong if it fails
+    check: Option<(Check, &'static str)>,
+}
+
+impl FieldSpec {
+    const fn new(name: &'static str, kind: FieldKind, required: bool) -> Self {
+        Self {
+            name,
+            kind,
+            required,
+            check: None,
+        }
+    }
+
+    const fn check(self, check: Check, problem: &'static str) -> Self {
+        Self {
+            check: Some((check, problem)),
+            ..self
+        }
+    }
+
+    /// Check that `value` is valid for this field.
+    /// Unlike [`FieldKind::validate`], the value is never included in the error
+    pub fn validate(&self, value: &str) -> Result<()> {
+        if let Some((check, problem)) = self.check {
+            if !check(value) {
+                return Err(Error::InvalidEntryField {
+                    field: self.name.to_string(),
+                    reason: problem.to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+const CARD_FIELDS: &[FieldSpec] = &[
+    FieldSpec::new("Number", FieldKind::Hidden, true)
+        .check(is_card_number, "not a valid card number"),
+    FieldSpec::new("Expiry", FieldKind::Text, true).check(is_expiry, "expected `MM/YY`"),
+    FieldSpec::new("CVV", FieldKind::Hidden, false).check(is_cvv, "expected 3 or 4 digits"),
+];
+const SSH_KEY_FIELDS: &[FieldSpec] = &[
+    FieldSpec::new("Private key", FieldKind::Hidden, true),
+    FieldSpec::new("Public key", FieldKind::Text, false),
+];
+const API_TOKEN_FIELDS: &[FieldSpec] = &[FieldSpec::new("Expires", FieldKind::Date, false)];
+const WIFI_FIELDS: &[FieldSpec] = &[FieldSpec::new("Security", FieldKind::Text, false)];
+const DATABASE_FIELDS: &[FieldSpec] = &[
+    FieldSpec::new("Port", FieldKind::Number, false)
+        .check(is_port, "expected a number from 1 to 65535"),
+    FieldSpec::new("Database", FieldKind::Text, false),
+];
+
+impl EntryKind {
+    /// A human-readable name, e.g. `Credit card`
+    pub fn label(self) -> &'static str {
+        match self {
+            EntryKind::Login => "Login",
+            EntryKind::SecureNote => "Secure note",
+            EntryKind::Card => "Credit card",
+            EntryKind::SshKey => "SSH key",
+            EntryKind::ApiToken => "API token",
+            EntryKind::Wifi => "Wi-Fi network",
+            EntryKind::Database => "Database",
+        }
+    }
+
+    /// What the entry's username is called, or `None` if it isn't used
+    pub fn username_label(self) -> Option<&'static str> {
+        match self {
+            EntryKind::Login | EntryKind::Database => Some("Username"),
+            EntryKind::Card => Some("Cardholder"),
+            EntryKind::SshKey => Some("User"),
+            EntryKind::ApiToken => Some("Account"),
+            EntryKind::SecureNote | EntryKind::Wifi => None,
+        }
+    }
+
+    /// What the entry's password is called, or `None` if it isn't used
+    pub fn password_label(self) -> Option<&'static str> {
+        match self {
+            EntryKind::Login | EntryKind::Database => Some("Password"),
+            EntryKind::Card => Some("PIN"),
+            EntryKind::SshKey | EntryKind::Wifi => Some("Passphrase"),
+            EntryKind::ApiToken => Some("Token"),
+            EntryKind::SecureNote => None,
+        }
+    }
+
+    /// What the entry's location is called, or `None` if it isn't used
+    pub fn location_label(self) -> Option<&'static str> {
+        match self {
+            EntryKind::Login => Some("Location"),
+            EntryKind::Card => Some("Issuer"),
+            EntryKind::SshKey | EntryKind::Database => Some("Host"),
+            EntryKind::ApiToken => Some("Service"),
+            EntryKind::Wifi => Some("SSID"),
+            EntryKind::SecureNote => None,
+        }
+    }
+
+    /// Whether the password should be strong, which isn't the case
+    /// for short numeric ones like a card's PIN
+    pub fn checks_strength(self) -> bool {
+        !matches!(self, EntryKind::SecureNote | EntryKind::Card)
+    }
+
+    /// The custom fields entries of this kind have
+    pub fn schema(self) -> &'static [FieldSpec] {
+        match self {
+            EntryKind::Login | EntryKind::SecureNote => &[],
+            EntryKind::Card => CARD_FIELDS,
+            EntryKind::SshKey => SSH_KEY_FIELDS,
+            EntryKind::ApiToken => API_TOKEN_FIELDS,
+            EntryKind::Wifi => WIFI_FIELDS,
+            EntryKind::Database => DATABASE_FIELDS,
+        }
+    }
+
+    /// The field in [`EntryKind::schema`] called `name`, ignoring case
+    pub fn field_spec(self, name: &str) -> Option<&'static FieldSpec> {
+        self.schema()
+            .iter()
+            .find(|spec| spec.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Check that `entry` has every field this kind needs,
+    /// & that they're all valid
+    pub fn validate(self, entry: &Entry) -> Result<()> {
+        for spec in self.schema() {
+            match entry.custom_field(spec.name) {
+                Some(field) => {
+                    if field.kind() != spec.kind {
+                        return Err(Error::InvalidEntryField {
+                            field: spec.name.to_string(),
+                            reason: format!("expected a {} field", spec.kind),
+                        });
+                    }
+                    spec.validate(field.value())?;
+                }
+                None if spec.required => {
+                    return Err(Error::MissingField {
+                        kind: self.label().to_string(),
+                        field: spec.name.to_string(),
+                    })
+                }
+                None => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 12 to 19 digits, ignoring spaces & dashes, with a valid Luhn check digit
+fn is_card_number(value: &str) -> bool {
+    let digits: Vec<u32> = value
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-'))
+        .map(|c| c.to_digit(10))
+        .collect::<Option<_>>()
+        .unwrap_or_default();
+    if !(12..=19).contains(&digits.len()) {
+        return false;
+    }
+
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(idx, &digit)| match (idx % 2, digit * 2) {
+            (0, _) => digit,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+
+    sum.is_multiple_of(10)
+}
+
+/// `MM/YY` or `MM/YYYY`
+fn is_expiry(value: &str) -> bool {
+    let Some((month, year)) = value.trim().split_once('/') else {
+        return false;
+    };
+
+    month.len() == 2
+        && month
+            .parse::<u32>()
+            .is_ok_and(|month| (1..=12).contains(&month))
+        && matches!(year.len(), 2 | 4)
+        && year.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_cvv(value: &str) -> bool {
+    matches!(value.len(), 3 | 4) && value.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_port(value: &str) -> bool {
+    value.trim().parse::<u16>().is_ok_and(|port| port > 0)
+}
+
+impl FromStr for EntryKind {
+    type Err = Error;
+
+    /// Parses `login`, `note`, `card`, `ssh-key`, `api-token`, `wifi` or `database`
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "login" => Ok(EntryKind::Login),
+            "note" | "secure-note" => Ok(EntryKind::SecureNote),
+            "card" => Ok(EntryKind::Card),
+            "ssh-key" | "ssh" => Ok(EntryKind::SshKey),
+            "api-token" | "token" => Ok(EntryKind::ApiToken),
+            "wifi" => Ok(EntryKind::Wifi),
+            "database" | "db" => Ok(EntryKind::Database),
+            _ => Err(Error::InvalidEntryKind {
+                kind: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for EntryKind {
+    /// The name [`EntryKind::from_str`] parses, e.g. `ssh-key`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntryKind::Login => write!(f, "login"),
+            EntryKind::SecureNote => write!(f, "note"),
+            EntryKind::Card => write!(f, "card"),
+            EntryKind::SshKey => write!(f, "ssh-key"),
+            EntryKind::ApiToken => write!(f, "api-token"),
+            EntryKind::Wifi => write!(f, "wifi"),
+            EntryKind::Database => write!(f, "database"),
+        }
+    }
+}
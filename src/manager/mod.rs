@@ -1,169 +1,428 @@
 pub mod entry;
+pub mod fields;
+pub(crate) mod format;
+pub mod kinds;
 pub mod notes;
-
-use arboard::Clipboard;
+pub mod otp;
+mod storage;
 
 use std::{
-    fs::{self, OpenOptions},
-    io::{Seek, SeekFrom},
-    path::PathBuf,
+    fmt, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
 };
 
-use crate::error::{Error, Result};
-use entry::Entry;
+use uuid::Uuid;
+use zeroize::Zeroizing;
 
-pub fn add(new: Entry, path: PathBuf) -> Result<()> {
-    let mut file = OpenOptions::new()
-        .create(true)
-        .read(true)
-        .write(true)
-        .open(path)?;
+use crate::{
+    crypto,
+    error::{Error, Result},
+};
+use entry::{Entry, EntrySummary};
+use format::{Migrated, VaultDocument};
+use storage::{backup_path, lock, write_atomic, LockMode, VaultLock};
+
+/// An open vault, read & decrypted into memory.
+///
+/// The vault file stays locked for as long as this exists,
+/// & changes only hit the disk when [`Vault::save`] is called
+pub struct Vault {
+    path: PathBuf,
+    master_pw: Zeroizing<String>,
+    document: VaultDocument,
+    mode: LockMode,
+    _lock: VaultLock,
+}
 
-    let mut entries = get_entries(&file)?;
+impl Vault {
+    /// Open the vault at `path` for reading & writing,
+    /// blocking any other process from opening it until dropped.
+    ///
+    /// A missing or empty file is treated as an empty vault
+    pub fn open(path: impl Into<PathBuf>, master_pw: &str) -> Result<Self> {
+        Self::open_with(path.into(), master_pw, LockMode::Exclusive)
+    }
 
-    if entries
-        .iter()
-        .any(|entry| entry.name.to_lowercase() == new.name.to_lowercase())
-    {
-        return Err(Error::EntryExists { name: new.name });
+    /// Open the vault at `path` for reading only.
+    /// Other processes can read the vault at the same time, but not modify it
+    pub fn open_read_only(path: impl Into<PathBuf>, master_pw: &str) -> Result<Self> {
+        Self::open_with(path.into(), master_pw, LockMode::Shared)
     }
 
-    entries.push(new);
+    fn open_with(path: PathBuf, master_pw: &str, mode: LockMode) -> Result<Self> {
+        let mut vault_lock = lock(&path, mode)?;
+        let mut loaded = read_vault(&path, master_pw)?;
+
+        if loaded.outdated.is_some() && mode == LockMode::Shared {
+            // Upgrading writes to the vault, which needs an exclusive lock.
+            // Another process may upgrade it while neither lock is held, so it's read again.
+            // The exclusive lock is then kept, since the vault could change if it were let go
+            drop(vault_lock);
+            vault_lock = lock(&path, LockMode::Exclusive)?;
+            loaded = read_vault(&path, master_pw)?;
+        }
+
+        let Loaded {
+            mut document,
+            outdated,
+        } = loaded;
+        if let Some((version, original)) = outdated {
+            upgrade(&path, &mut document, version, &original, master_pw)?;
+        }
 
-    file.set_len(0)?;
-    file.seek(SeekFrom::Start(0))?;
-    serde_json::to_writer(&mut file, &entries)?;
+        Ok(Self {
+            path,
+            master_pw: Zeroizing::new(master_pw.to_string()),
+            document,
+            mode,
+            _lock: vault_lock,
+        })
+    }
 
-    Ok(())
-}
+    /// Returns `true` if the vault at `path` is already encrypted.
+    ///
+    /// Missing, empty & legacy plaintext vaults are encrypted with
+    /// whatever master password they're next opened with,
+    /// so frontends should have that password confirmed first
+    pub fn is_encrypted(path: impl AsRef<Path>) -> Result<bool> {
+        match fs::read(path) {
+            Ok(data) => Ok(crypto::is_encrypted(&data)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
 
-pub fn remove(name: &str, path: PathBuf) -> Result<()> {
-    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    /// Encrypt the vault & write it back to disk
+    pub fn save(&mut self) -> Result<()> {
+        if self.mode != LockMode::Exclusive {
+            return Err(Error::ReadOnly);
+        }
+
+        write_vault(&self.path, &mut self.document, &self.master_pw, true)
+    }
 
-    let mut entries = get_entries(&file)?;
-    file.set_len(0)?;
-    file.seek(SeekFrom::Start(0))?;
+    /// All entries in the vault, in the order they were added
+    pub fn entries(&self) -> &[Entry] {
+        &self.document.entries
+    }
 
-    if !entries.iter().any(|entry| entry == name) {
-        return Err(Error::EntryDoesntExist {
-            name: name.to_string(),
-        });
+    /// A summary of every entry in the vault, in the order they were added
+    pub fn list(&self) -> Vec<EntrySummary> {
+        self.document.entries.iter().map(Entry::summary).collect()
     }
 
-    entries = entries
-        .iter()
-        .filter(|entry| *entry != name)
-        .cloned()
-        .collect::<Vec<Entry>>();
+    /// Returns `true` if `reference` picks out exactly one entry.
+    /// See [`Vault::get`] for what a reference can be
+    pub fn contains(&self, reference: &str) -> bool {
+        self.find(reference).is_ok()
+    }
 
-    serde_json::to_writer(&mut file, &entries)?;
+    /// Every entry `reference` could mean. More than one means it's ambiguous,
+    /// & one of the entries' IDs or [`Entry::reference`]s should be used instead
+    pub fn matches(&self, reference: &str) -> Vec<&Entry> {
+        self.match_indices(reference)
+            .into_iter()
+            .map(|idx| &self.document.entries[idx])
+            .collect()
+    }
 
-    Ok(())
-}
+    /// Get the entry `reference` refers to, which can be:
+    ///
+    /// * The entry's ID
+    /// * Its name, ignoring case, if no other entry has the same name
+    /// * `<name>:<username>`, to pick between entries with the same name
+    pub fn get(&self, reference: &str) -> Result<&Entry> {
+        Ok(&self.document.entries[self.find(reference)?])
+    }
 
-pub fn show(name: &str, path: PathBuf, copy_passwd: bool) -> Result<()> {
-    let file = OpenOptions::new().read(true).open(path)?;
+    fn get_mut(&mut self, reference: &str) -> Result<&mut Entry> {
+        let idx = self.find(reference)?;
+        Ok(&mut self.document.entries[idx])
+    }
+
+    /// The index of the only entry `reference` refers to
+    fn find(&self, reference: &str) -> Result<usize> {
+        match self.match_indices(reference)[..] {
+            [idx] => Ok(idx),
+            [] => Err(Error::EntryDoesntExist {
+                name: reference.to_string(),
+            }),
+            ref several => Err(Error::AmbiguousEntry {
+                name: reference.to_string(),
+                matches: several
+                    .iter()
+                    .map(|&idx| self.document.entries[idx].reference())
+                    .collect(),
+            }),
+        }
+    }
+
+    fn match_indices(&self, reference: &str) -> Vec<usize> {
+        let entries = &self.document.entries;
+        let indices = |matches: &dyn Fn(&Entry) -> bool| -> Vec<usize> {
+            (0..entries.len())
+                .filter(|&idx| matches(&entries[idx]))
+                .collect()
+        };
+
+        if let Ok(id) = Uuid::parse_str(reference.trim()) {
+            let by_id = indices(&|entry| entry.id == id);
+            if !by_id.is_empty() {
+                return by_id;
+            }
+        }
 
-    let entries = get_entries(&file)?;
+        let by_name = indices(&|entry| entry.is_called(reference));
+        if !by_name.is_empty() {
+            return by_name;
+        }
 
-    for entry in entries {
-        if entry == name {
-            println!("{entry}");
-            if copy_passwd {
-                let mut ctx = Clipboard::new()?;
-                ctx.set_text(entry.show_password())?;
+        match reference.split_once(':') {
+            Some((name, username)) => {
+                indices(&|entry| entry.is_called(name) && entry.username == username)
             }
+            None => Vec::new(),
         }
     }
 
-    Ok(())
-}
+    /// The index of an entry other than `except` with the same name & username as `entry`
+    fn clash(&self, entry: &Entry, except: Option<usize>) -> Option<usize> {
+        (0..self.document.entries.len()).find(|&idx| {
+            let other = &self.document.entries[idx];
+            Some(idx) != except && other.is_called(&entry.name) && other.username == entry.username
+        })
+    }
 
-pub fn list(path: PathBuf) -> Result<()> {
-    let file = OpenOptions::new().read(true).open(path)?;
+    /// Add a new entry, failing if one with the same name & username already exists
+    /// or it doesn't have the fields its kind needs
+    pub fn add(&mut self, new: Entry) -> Result<()> {
+        if self.clash(&new, None).is_some() {
+            return Err(Error::EntryExists {
+                name: new.reference(),
+            });
+        }
+        new.validate()?;
 
-    let entries = get_entries(&file)?;
+        self.document.entries.push(new);
 
-    for entry in entries {
-        println!("{} [{}]", entry.name, entry.location);
+        Ok(())
     }
 
-    Ok(())
-}
+    /// Remove the entry `reference` refers to, returning it
+    pub fn remove(&mut self, reference: &str) -> Result<Entry> {
+        let idx = self.find(reference)?;
 
-pub fn edit(name: &str, new: Entry, path: PathBuf) -> Result<()> {
-    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
-
-    let entries = get_entries(&file)?;
-
-    if !entries.iter().any(|entry| entry == name) {
-        return Err(Error::EntryDoesntExist {
-            name: name.to_string(),
-        });
-    }
-
-    let entries: Vec<Entry> = entries
-        .iter()
-        .map(|entry| {
-            if entry == name {
-                let new_name = if new.name.is_empty() {
-                    &entry.name
-                } else {
-                    &new.name
-                };
-                let new_username = if new.username.is_empty() {
-                    &entry.username
-                } else {
-                    &new.username
-                };
-                let new_pw = if new.show_password().is_empty() {
-                    entry.show_password()
-                } else {
-                    new.show_password()
-                };
-                let new_location = if new.location.is_empty() {
-                    &entry.location
-                } else {
-                    &new.location
-                };
-
-                Entry::new(
-                    new_name.clone(),
-                    new_location.clone(),
-                    new_username.clone(),
-                    new_pw,
-                )
-            } else {
-                entry.clone()
-            }
-        })
-        .collect();
+        Ok(self.document.entries.remove(idx))
+    }
 
-    file.set_len(0)?;
-    file.seek(SeekFrom::Start(0))?;
+    /// Replace the fields of the entry `reference` refers to with those in `new`.
+    /// Empty fields in `new` are left unchanged, & notes are kept
+    pub fn edit(&mut self, reference: &str, new: Entry) -> Result<()> {
+        let idx = self.find(reference)?;
+        let entry = &self.document.entries[idx];
 
-    serde_json::to_writer(&mut file, &entries)?;
-    Ok(())
-}
+        let mut renamed = entry.clone();
+        if !new.name.is_empty() {
+            renamed.name.clone_from(&new.name);
+        }
+        if !new.username.is_empty() {
+            renamed.username.clone_from(&new.username);
+        }
+        if self.clash(&renamed, Some(idx)).is_some() {
+            return Err(Error::EntryExists {
+                name: renamed.reference(),
+            });
+        }
 
-pub fn entry_exists(search_name: &str, path: &PathBuf) -> Result<bool> {
-    let file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .read(true)
-        .open(path)?;
+        let entry = &mut self.document.entries[idx];
+        entry.name = renamed.name;
+        entry.username = renamed.username;
+        if !new.password.is_empty() && new.password != entry.password {
+            entry.password = new.password;
+            entry.password_changed = new.password_changed;
+        }
+        if !new.location.is_empty() {
+            entry.location = new.location;
+        }
+
+        Ok(())
+    }
+}
 
-    let entries = get_entries(&file)?;
+impl fmt::Debug for Vault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Vault")
+            .field("path", &self.path)
+            .field("document", &self.document)
+            .field("mode", &self.mode)
+            .finish_non_exhaustive()
+    }
+}
 
-    Ok(entries.iter().any(|entry| entry == search_name))
+/// A vault as read from disk, which may still need upgrading
+struct Loaded {
+    document: VaultDocument,
+    /// The version & original contents of a vault in an older format,
+    /// which has to be written back in the current format
+    outdated: Option<(u32, Zeroizing<Vec<u8>>)>,
 }
 
-fn get_entries(file: &fs::File) -> Result<Vec<Entry>> {
-    let entries: Vec<Entry> = match serde_json::from_reader(file) {
-        Ok(list) => list,
-        Err(e) if e.is_eof() => Vec::new(),
+/// Read & decrypt the vault stored at `path`, without writing anything
+///
+/// A missing or empty file is treated as an empty vault.
+/// Vaults in an older format are upgraded in memory, & need passing to [`upgrade`]
+fn read_vault(path: &Path, master_pw: &str) -> Result<Loaded> {
+    // Legacy vaults aren't encrypted, so this may be plaintext
+    let data = match fs::read(path) {
+        Ok(data) => Zeroizing::new(data),
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Loaded::empty()),
         Err(e) => return Err(e.into()),
     };
 
-    Ok(entries)
+    if data.iter().all(u8::is_ascii_whitespace) {
+        return Ok(Loaded::empty());
+    }
+
+    let Migrated {
+        document,
+        from_version,
+    } = if crypto::is_encrypted(&data) {
+        format::migrate(&crypto::decrypt(&data, master_pw)?)?
+    } else {
+        format::migrate(&data)?
+    };
+
+    Ok(Loaded {
+        document,
+        outdated: from_version.map(|version| (version, data)),
+    })
+}
+
+impl Loaded {
+    fn empty() -> Self {
+        Self {
+            document: VaultDocument::new(Vec::new()),
+            outdated: None,
+        }
+    }
+}
+
+/// Write back a vault that was in an older format, keeping the `original` file
+/// alongside it as `<file>.v<version>.bak`. Needs an exclusive lock.
+///
+/// Legacy plaintext vaults are encrypted before being backed up,
+/// so no unencrypted copy is left on disk
+fn upgrade(
+    path: &Path,
+    document: &mut VaultDocument,
+    version: u32,
+    original: &[u8],
+    master_pw: &str,
+) -> Result<()> {
+    let encrypted;
+    let backup = if crypto::is_encrypted(original) {
+        original
+    } else {
+        encrypted = crypto::encrypt(original, master_pw)?;
+        &encrypted
+    };
+    write_atomic(
+        &backup_path(path, &format!("v{version}.bak")),
+        backup,
+        false,
+    )?;
+
+    // The backup above is the only one needed
+    write_vault(path, document, master_pw, false)
+}
+
+/// Encrypt `vault` & write it to `path`, keeping the previous version as `<file>.bak` if `backup` is set
+fn write_vault(
+    path: &Path,
+    vault: &mut VaultDocument,
+    master_pw: &str,
+    backup: bool,
+) -> Result<()> {
+    vault.touch();
+    let plaintext = Zeroizing::new(serde_json::to_vec(vault)?);
+    write_atomic(path, &crypto::encrypt(&plaintext, master_pw)?, backup)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn login(name: &str, username: &str) -> Entry {
+        Entry::new(
+            name.to_string(),
+            String::new(),
+            username.to_string(),
+            "hunter2".to_string(),
+        )
+    }
+
+    /// A new vault holding `entries`. It's never saved, so nothing is encrypted
+    fn vault_with(entries: &[(&str, &str)]) -> (TempDir, Vault) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut vault = Vault::open(dir.path().join("vault.json"), "master password").unwrap();
+        for (name, username) in entries {
+            vault.add(login(name, username)).unwrap();
+        }
+
+        (dir, vault)
+    }
+
+    #[test]
+    fn finds_entries_by_id_or_name() {
+        let (_dir, vault) = vault_with(&[("GitHub", "octocat"), ("GitLab", "octocat")]);
+        let id = vault.entries()[1].id();
+
+        assert_eq!(vault.get(&id.to_string()).unwrap().name(), "GitLab");
+        assert_eq!(vault.get("github").unwrap().name(), "GitHub");
+        assert_eq!(vault.get("GITLAB").unwrap().name(), "GitLab");
+        assert!(matches!(
+            vault.get("Gitea"),
+            Err(Error::EntryDoesntExist { .. })
+        ));
+    }
+
+    #[test]
+    fn picks_between_shared_names_by_username() {
+        let (_dir, vault) = vault_with(&[("GitHub", "octocat"), ("GitHub", "work")]);
+
+        assert_eq!(vault.get("github:work").unwrap().username(), "work");
+        assert_eq!(vault.get("GitHub:octocat").unwrap().username(), "octocat");
+        assert!(!vault.contains("GitHub:other"));
+        assert_eq!(vault.matches("github").len(), 2);
+
+        match vault.get("github") {
+            Err(Error::AmbiguousEntry { name, matches }) => {
+                assert_eq!(name, "github");
+                assert_eq!(matches, ["GitHub:octocat", "GitHub:work"]);
+            }
+            other => panic!("expected an ambiguous entry, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn rejects_clashing_entries() {
+        let (_dir, mut vault) = vault_with(&[("GitHub", "octocat"), ("GitHub", "work")]);
+
+        assert!(matches!(
+            vault.add(login("github", "octocat")),
+            Err(Error::EntryExists { .. })
+        ));
+        assert!(matches!(
+            vault.edit("GitHub:work", login("", "octocat")),
+            Err(Error::EntryExists { .. })
+        ));
+        assert_eq!(vault.get("GitHub:work").unwrap().username(), "work");
+
+        vault.edit("GitHub:work", login("GitHub", "work")).unwrap();
+        vault.edit("GitHub:work", login("", "personal")).unwrap();
+        assert!(vault.contains("GitHub:personal"));
+    }
 }
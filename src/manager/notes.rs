@@ -1,120 +1,46 @@
-use std::{
-    fs::OpenOptions,
-    io::{Seek as _, SeekFrom},
-    path::PathBuf,
-};
-
 use crate::{
     error::{Error, Result},
-    manager::get_entries,
+    manager::Vault,
 };
 
-pub fn add(name: &str, note: String, path: PathBuf) -> Result<()> {
-    let mut file = OpenOptions::new().write(true).read(true).open(path)?;
-    let mut entries = get_entries(&file)?;
-
-    if !entries.iter().any(|entry| entry == name) {
-        return Err(Error::EntryDoesntExist {
-            name: name.to_string(),
-        });
+impl Vault {
+    /// The notes for the entry called `entry_name`
+    pub fn notes(&self, entry_name: &str) -> Result<&[String]> {
+        Ok(&self.get(entry_name)?.notes)
     }
 
-    for entry in entries.iter_mut() {
-        if entry == name {
-            entry.notes.push(note.clone());
-        }
-    }
-
-    file.set_len(0)?;
-    file.seek(SeekFrom::Start(0))?;
-
-    serde_json::to_writer(file, &entries)?;
+    /// Add a note to the entry called `entry_name`
+    pub fn add_note(&mut self, entry_name: &str, note: String) -> Result<()> {
+        self.get_mut(entry_name)?.notes.push(note);
 
-    Ok(())
-}
-
-pub fn list(entry_name: &str, path: PathBuf) -> Result<()> {
-    let file = OpenOptions::new().read(true).open(path)?;
-    let entries = get_entries(&file)?;
-
-    if !entries.iter().any(|entry| entry == entry_name) {
-        return Err(Error::EntryDoesntExist {
-            name: entry_name.to_string(),
-        });
-    }
-
-    for entry in entries {
-        if entry == entry_name {
-            println!("Notes for {}:", entry.name);
-            for (idx, note) in entry.notes.iter().enumerate() {
-                println!("{idx}: {note}");
-            }
-        }
+        Ok(())
     }
 
-    Ok(())
-}
-
-pub fn remove(entry_name: &str, note_id: usize, path: PathBuf) -> Result<()> {
-    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
-    let mut entries = get_entries(&file)?;
-
-    if !entries.iter().any(|entry| entry == entry_name) {
-        return Err(Error::EntryDoesntExist {
-            name: entry_name.to_string(),
-        });
-    }
+    /// Remove the note with the given id from the entry called `entry_name`,
+    /// returning it
+    pub fn remove_note(&mut self, entry_name: &str, note_id: usize) -> Result<String> {
+        let entry = self.get_mut(entry_name)?;
 
-    for entry in &mut entries {
-        if entry == entry_name {
-            if note_id >= entry.notes.len() {
-                return Err(Error::NoteIdOOB {
-                    id: note_id,
-                    len: entry.notes.len(),
-                });
-            } else {
-                entry.notes.remove(note_id);
-            }
-            break;
+        if note_id >= entry.notes.len() {
+            return Err(Error::NoteIdOOB {
+                id: note_id,
+                len: entry.notes.len(),
+            });
         }
-    }
-
-    file.set_len(0)?;
-    file.seek(SeekFrom::Start(0))?;
-
-    serde_json::to_writer(file, &entries)?;
 
-    Ok(())
-}
-
-pub fn edit(entry_name: &str, note_id: usize, new_note: String, path: PathBuf) -> Result<()> {
-    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
-    let mut entries = get_entries(&file)?;
-
-    if !entries.iter().any(|entry| entry == entry_name) {
-        return Err(Error::EntryDoesntExist {
-            name: entry_name.to_string(),
-        });
+        Ok(entry.notes.remove(note_id))
     }
 
-    for entry in &mut entries {
-        if entry == entry_name {
-            if note_id >= entry.notes.len() {
-                return Err(Error::NoteIdOOB {
-                    id: note_id,
-                    len: entry.notes.len(),
-                });
-            } else {
-                entry.notes[note_id] = new_note;
-            }
+    /// Replace the note with the given id in the entry called `entry_name`
+    pub fn edit_note(&mut self, entry_name: &str, note_id: usize, new_note: String) -> Result<()> {
+        let entry = self.get_mut(entry_name)?;
+        let len = entry.notes.len();
 
-            break;
+        match entry.notes.get_mut(note_id) {
+            Some(note) => *note = new_note,
+            None => return Err(Error::NoteIdOOB { id: note_id, len }),
         }
-    }
-
-    file.set_len(0)?;
-    file.seek(SeekFrom::Start(0))?;
-    serde_json::to_writer(file, &entries)?;
 
-    Ok(())
+        Ok(())
+    }
 }
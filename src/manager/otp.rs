@@ -0,0 +1,30 @@
+use crate::{
+    error::{Error, Result},
+    manager::Vault,
+    otp::{Otp, OtpCode},
+};
+
+impl Vault {
+    /// Set or remove the one-time password secret of the entry called `entry_name`
+    pub fn set_otp(&mut self, entry_name: &str, otp: Option<Otp>) -> Result<()> {
+        self.get_mut(entry_name)?.otp = otp;
+
+        Ok(())
+    }
+
+    /// Get the one-time password to use now for the entry called `entry_name`.
+    ///
+    /// For HOTP, this moves the counter on so the same code is never given twice.
+    /// Save the vault before using the code, so the counter is never reused
+    pub fn next_otp(&mut self, entry_name: &str) -> Result<OtpCode> {
+        let entry = self.get_mut(entry_name)?;
+        let otp = entry.otp.as_mut().ok_or_else(|| Error::NoOtp {
+            name: entry_name.to_string(),
+        })?;
+
+        let code = otp.current();
+        otp.advance();
+
+        Ok(code)
+    }
+}
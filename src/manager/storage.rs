@@ -0,0 +1,178 @@
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use tempfile::NamedTempFile;
+
+use crate::error::{Error, Result};
+
+/// How long to wait for another PassMan process to release the vault
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// For reading the vault. Any number of shared locks can be held at once
+    Shared,
+    /// For modifying the vault. Excludes all other locks
+    Exclusive,
+}
+
+/// An advisory lock on a vault, released when dropped
+#[derive(Debug)]
+pub struct VaultLock {
+    _file: File,
+}
+
+/// Lock the vault at `path`, waiting up to [`LOCK_TIMEOUT`]
+/// for any conflicting locks to be released.
+///
+/// The lock is taken on a `<file>.lock` sidecar rather than the vault itself,
+/// since [`write_atomic`] replaces the vault file & would drop any lock held on it
+pub fn lock(path: &Path, mode: LockMode) -> Result<VaultLock> {
+    let lock_path = backup_path(path, "lock");
+    let file = match OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+    {
+        Ok(file) => file,
+        // A vault in a read-only directory can still be read. Nothing can replace it
+        // while the directory stays that way, so locking the vault itself is enough
+        // if there's no lock file to share
+        Err(e) if mode == LockMode::Shared => File::open(&lock_path)
+            .or_else(|_| File::open(path))
+            .map_err(|_| e)?,
+        Err(e) => return Err(e.into()),
+    };
+
+    let start = Instant::now();
+    loop {
+        let attempt = match mode {
+            LockMode::Shared => file.try_lock_shared(),
+            LockMode::Exclusive => file.try_lock(),
+        };
+
+        match attempt {
+            Ok(()) => return Ok(VaultLock { _file: file }),
+            Err(TryLockError::WouldBlock) if start.elapsed() < LOCK_TIMEOUT => {
+                thread::sleep(LOCK_RETRY_INTERVAL)
+            }
+            Err(TryLockError::WouldBlock) => return Err(Error::LockTimeout { path: lock_path }),
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+    }
+}
+
+/// Replace the contents of `path` without ever leaving it
+/// empty or half-written.
+///
+/// The new contents are written to a temporary file in the same directory
+/// & synced to disk before being renamed over the original,
+/// which is kept as `<file>.bak` if `backup` is set
+///
+/// If anything goes wrong before the rename, the original is left untouched
+pub fn write_atomic(path: &Path, contents: &[u8], backup: bool) -> Result<()> {
+    write_atomic_with(path, backup, |file| file.write_all(contents))
+}
+
+/// [`write_atomic`], with `write` filling in the temporary file
+fn write_atomic_with(
+    path: &Path,
+    backup: bool,
+    write: impl FnOnce(&mut File) -> io::Result<()>,
+) -> Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut temp = NamedTempFile::new_in(dir)?;
+    write(temp.as_file_mut())?;
+    temp.as_file().sync_all()?;
+
+    if backup {
+        match fs::copy(path, backup_path(path, "bak")) {
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    temp.persist(path).map_err(|e| e.error)?;
+    sync_dir(dir)?;
+
+    Ok(())
+}
+
+/// `path` with `extension` appended to its file name,
+/// e.g. `.passman.json` -> `.passman.json.bak`
+pub fn backup_path(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(extension);
+
+    path.with_file_name(name)
+}
+
+/// Make sure a rename within `dir` has actually hit the disk
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> Result<()> {
+    fs::File::open(dir)?.sync_all()?;
+
+    Ok(())
+}
+
+/// Windows doesn't allow opening directories as files,
+/// & `MoveFileEx` (used for the rename) is already durable
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_write_keeps_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.json");
+        fs::write(&path, "original").unwrap();
+        fs::write(backup_path(&path, "bak"), "backup").unwrap();
+
+        let result = write_atomic_with(&path, true, |file| {
+            file.write_all(b"half of the new")?;
+            Err(io::Error::other("disk full"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "original");
+        assert_eq!(
+            fs::read_to_string(backup_path(&path, "bak")).unwrap(),
+            "backup"
+        );
+        // The temporary file is cleaned up too
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn write_keeps_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.json");
+        fs::write(&path, "original").unwrap();
+
+        write_atomic(&path, b"new", true).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(
+            fs::read_to_string(backup_path(&path, "bak")).unwrap(),
+            "original"
+        );
+    }
+}
@@ -0,0 +1,478 @@
+use pass_man::{
+    audit::AuditReport,
+    manager::{
+        entry::{EntrySummary, PasswordMode, PASSWORD_MASK},
+        fields::{CustomField, FieldKind},
+        kinds::EntryKind,
+    },
+    otp::OtpCode,
+    strength::Strength,
+    Entry, Uuid,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use zeroize::Zeroizing;
+
+use crate::{app_error::AppError, cli::Format};
+
+/// Turns the results of commands into text to print.
+///
+/// Every method returns the complete output,
+/// including any trailing newline
+pub trait Renderer {
+    /// A single entry, as shown by `show`
+    fn entry(&self, entry: &Entry, mode: PasswordMode) -> String;
+    /// Every entry in the vault, as shown by `list`
+    fn entries(&self, entries: &[EntrySummary]) -> String;
+    /// The notes for an entry, as shown by `notes list`
+    fn notes(&self, entry: &Entry) -> String;
+    /// The custom fields of an entry, as shown by `field list`
+    fn fields(&self, entry: &Entry, mode: PasswordMode) -> String;
+    /// A one-time password, as shown by `otp`
+    fn otp(&self, code: &OtpCode) -> String;
+    /// The results of `audit`
+    fn audit(&self, report: &AuditReport) -> String;
+    /// A newly generated password & how strong it is
+    fn password(&self, password: &str, strength: &Strength) -> String;
+    /// Confirmation that a command succeeded
+    fn message(&self, message: &str) -> String;
+    /// Something the user should know about that didn't stop the command,
+    /// printed to stderr
+    fn warning(&self, warning: &str) -> String;
+    /// A command failing, printed to stderr
+    fn error(&self, error: &AppError) -> String;
+}
+
+/// Get the renderer for the given output format
+pub fn renderer(format: Format) -> &'static dyn Renderer {
+    match format {
+        Format::Plain => &Plain,
+        Format::Json => &Json,
+        Format::Tsv => &Tsv,
+    }
+}
+
+/// The value of `field`, or [`PASSWORD_MASK`] if it's hidden & `mode` is masked
+fn field_value(field: &CustomField, mode: PasswordMode) -> &str {
+    match mode {
+        PasswordMode::Masked if field.is_hidden() => PASSWORD_MASK,
+        _ => field.value(),
+    }
+}
+
+/// Human-readable output, matching what PassMan has always printed
+pub struct Plain;
+
+impl Renderer for Plain {
+    fn entry(&self, entry: &Entry, mode: PasswordMode) -> String {
+        format!("{}\n", entry.display(mode))
+    }
+
+    fn entries(&self, entries: &[EntrySummary]) -> String {
+        // Entries sharing a name are shown as `<name>:<username>`,
+        // which is what's needed to pick one of them
+        let shared = |name: &str| {
+            entries
+                .iter()
+                .filter(|other| other.name.to_lowercase() == name.to_lowercase())
+                .count()
+                > 1
+        };
+
+        entries
+            .iter()
+            .map(|entry| {
+                let name = if shared(&entry.name) {
+                    entry.reference()
+                } else {
+                    entry.name.clone()
+                };
+
+                match entry.kind {
+                    EntryKind::Login => format!("{name} [{}]\n", entry.location),
+                    kind if kind.location_label().is_none() || entry.location.is_empty() => {
+                        format!("{name} ({})\n", kind.label())
+                    }
+                    kind => format!("{name} ({}) [{}]\n", kind.label(), entry.location),
+                }
+            })
+            .collect()
+    }
+
+    fn notes(&self, entry: &Entry) -> String {
+        let mut out = format!("Notes for {}:\n", entry.name());
+        for (idx, note) in entry.notes().iter().enumerate() {
+            out.push_str(&format!("{idx}: {note}\n"));
+        }
+
+        out
+    }
+
+    fn fields(&self, entry: &Entry, mode: PasswordMode) -> String {
+        let mut out = format!("Fields for {}:\n", entry.name());
+        for field in entry.fields() {
+            out.push_str(&format!(
+                "{} ({}): {}\n",
+                field.name(),
+                field.kind(),
+                field_value(field, mode)
+            ));
+        }
+
+        out
+    }
+
+    fn otp(&self, code: &OtpCode) -> String {
+        match (code.expires_in, code.counter) {
+            (Some(expires_in), _) => format!("{} (expires in {expires_in} seconds)\n", *code.code),
+            (None, Some(counter)) => format!("{} (counter {counter})\n", *code.code),
+            (None, None) => format!("{}\n", *code.code),
+        }
+    }
+
+    fn audit(&self, report: &AuditReport) -> String {
+        let summary = &report.summary;
+        let mut out = format!(
+            "Audited {} entries & found {} problems\n",
+            report.entries,
+            report.total()
+        );
+        for (count, problem) in [
+            (summary.reused, "reused passwords"),
+            (summary.breached, "breached passwords"),
+            (summary.weak, "weak passwords"),
+            (summary.old, "old passwords"),
+            (summary.empty_username, "empty usernames"),
+            (summary.duplicate_location, "duplicate locations"),
+        ] {
+            if count > 0 {
+                out.push_str(&format!("   {count} {problem}\n"));
+            }
+        }
+
+        for entry in &report.findings {
+            out.push_str(&format!("\n`{}`:\n", entry.entry));
+            for finding in &entry.findings {
+                out.push_str(&format!("   {finding}\n"));
+            }
+        }
+
+        out
+    }
+
+    fn password(&self, password: &str, strength: &Strength) -> String {
+        format!(
+            "{password}\nStrength: {} (~{} bits, {} bits of charset entropy)\n",
+            strength.label, strength.entropy_bits, strength.charset_bits
+        )
+    }
+
+    fn message(&self, message: &str) -> String {
+        format!("{message}\n")
+    }
+
+    fn warning(&self, warning: &str) -> String {
+        format!("Warning: {warning}\n")
+    }
+
+    fn error(&self, error: &AppError) -> String {
+        format!("Error: {error}\n")
+    }
+}
+
+/// A single JSON value per command, followed by a newline
+pub struct Json;
+
+/// A single entry, borrowing its secrets so that they're only ever
+/// copied into the output, rather than into a `Value` that isn't wiped
+#[derive(Serialize)]
+struct JsonEntry<'a> {
+    id: Uuid,
+    name: &'a str,
+    #[serde(rename = "type")]
+    kind: EntryKind,
+    username: &'a str,
+    password: Option<&'a str>,
+    location: &'a str,
+    notes: &'a [String],
+    fields: Vec<JsonField<'a>>,
+    strength: Option<Strength>,
+}
+
+/// A custom field, with hidden ones' values as `null` unless revealed
+#[derive(Serialize)]
+struct JsonField<'a> {
+    name: &'a str,
+    #[serde(rename = "type")]
+    kind: FieldKind,
+    value: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct JsonFields<'a> {
+    entry: &'a str,
+    fields: Vec<JsonField<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonOtp<'a> {
+    code: &'a str,
+    expires_in: Option<u64>,
+    counter: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct JsonPassword<'a> {
+    password: &'a str,
+    strength: &'a Strength,
+}
+
+impl Json {
+    fn render<T: Serialize>(value: &T) -> String {
+        let json = serde_json::to_string(value).expect("JSON output only has string keys");
+        format!("{json}\n")
+    }
+
+    fn fields_json(entry: &Entry, mode: PasswordMode) -> Vec<JsonField<'_>> {
+        entry
+            .fields()
+            .iter()
+            .map(|field| JsonField {
+                name: field.name(),
+                kind: field.kind(),
+                value: match mode {
+                    PasswordMode::Masked if field.is_hidden() => None,
+                    _ => Some(field.value()),
+                },
+            })
+            .collect()
+    }
+}
+
+impl Renderer for Json {
+    fn entry(&self, entry: &Entry, mode: PasswordMode) -> String {
+        let password = entry.show_password();
+        // Cards' PINs & entries without a password have no strength worth showing
+        let strength =
+            (entry.kind().checks_strength() && !password.is_empty()).then(|| entry.strength());
+
+        Self::render(&JsonEntry {
+            id: entry.id(),
+            name: entry.name(),
+            kind: entry.kind(),
+            username: entry.username(),
+            password: match mode {
+                PasswordMode::Masked => None,
+                PasswordMode::Revealed => Some(&password),
+            },
+            location: entry.location(),
+            notes: entry.notes(),
+            fields: Self::fields_json(entry, mode),
+            strength,
+        })
+    }
+
+    fn entries(&self, entries: &[EntrySummary]) -> String {
+        Self::render(&Value::Array(
+            entries
+                .iter()
+                .map(|entry| {
+                    json!({
+                        "id": entry.id,
+                        "name": entry.name,
+                        "type": entry.kind,
+                        "username": entry.username,
+                        "location": entry.location,
+                        "notes": entry.notes,
+                    })
+                })
+                .collect(),
+        ))
+    }
+
+    fn notes(&self, entry: &Entry) -> String {
+        Self::render(&json!({
+            "entry": entry.name(),
+            "notes": entry
+                .notes()
+                .iter()
+                .enumerate()
+                .map(|(id, note)| json!({ "id": id, "note": note }))
+                .collect::<Vec<_>>(),
+        }))
+    }
+
+    fn fields(&self, entry: &Entry, mode: PasswordMode) -> String {
+        Self::render(&JsonFields {
+            entry: entry.name(),
+            fields: Self::fields_json(entry, mode),
+        })
+    }
+
+    fn otp(&self, code: &OtpCode) -> String {
+        Self::render(&JsonOtp {
+            code: &code.code,
+            expires_in: code.expires_in,
+            counter: code.counter,
+        })
+    }
+
+    fn audit(&self, report: &AuditReport) -> String {
+        Self::render(report)
+    }
+
+    fn password(&self, password: &str, strength: &Strength) -> String {
+        Self::render(&JsonPassword { password, strength })
+    }
+
+    fn message(&self, message: &str) -> String {
+        Self::render(&json!({ "message": message }))
+    }
+
+    fn warning(&self, warning: &str) -> String {
+        Self::render(&json!({ "warning": warning }))
+    }
+
+    fn error(&self, error: &AppError) -> String {
+        Self::render(&json!({
+            "error": {
+                "code": error.code(),
+                "message": error.to_string(),
+            }
+        }))
+    }
+}
+
+/// Tab-separated values with no header row.
+/// Tabs, newlines & backslashes within fields are escaped as `\t`, `\n` & `\\`
+pub struct Tsv;
+
+impl Tsv {
+    /// Escapes the fields straight into a row that's big enough
+    /// to never be reallocated, so that no unwiped copies of secrets are left behind
+    fn row<S: AsRef<str>>(fields: &[S]) -> String {
+        let mut row = String::with_capacity(fields.iter().map(|f| f.as_ref().len() * 2 + 1).sum());
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                row.push('\t');
+            }
+            for c in field.as_ref().chars() {
+                match c {
+                    '\\' => row.push_str("\\\\"),
+                    '\t' => row.push_str("\\t"),
+                    '\n' => row.push_str("\\n"),
+                    c => row.push(c),
+                }
+            }
+        }
+        row.push('\n');
+
+        row
+    }
+}
+
+impl Renderer for Tsv {
+    fn entry(&self, entry: &Entry, mode: PasswordMode) -> String {
+        let password = match mode {
+            PasswordMode::Masked => Zeroizing::new(PASSWORD_MASK.to_string()),
+            PasswordMode::Revealed => entry.show_password(),
+        };
+
+        Self::row(&[
+            entry.name(),
+            entry.username(),
+            &password,
+            entry.location(),
+            &entry.notes().len().to_string(),
+            &entry.kind().to_string(),
+            &entry.id().to_string(),
+        ])
+    }
+
+    fn entries(&self, entries: &[EntrySummary]) -> String {
+        entries
+            .iter()
+            .map(|entry| {
+                Self::row(&[
+                    &entry.name,
+                    &entry.username,
+                    &entry.location,
+                    &entry.notes.to_string(),
+                    &entry.kind.to_string(),
+                    &entry.id.to_string(),
+                ])
+            })
+            .collect()
+    }
+
+    fn notes(&self, entry: &Entry) -> String {
+        entry
+            .notes()
+            .iter()
+            .enumerate()
+            .map(|(id, note)| Self::row(&[&id.to_string(), note]))
+            .collect()
+    }
+
+    fn fields(&self, entry: &Entry, mode: PasswordMode) -> String {
+        entry
+            .fields()
+            .iter()
+            .map(|field| {
+                Self::row(&[
+                    field.name(),
+                    &field.kind().to_string(),
+                    field_value(field, mode),
+                ])
+            })
+            .collect()
+    }
+
+    fn otp(&self, code: &OtpCode) -> String {
+        let optional =
+            |value: Option<u64>| value.map(|value| value.to_string()).unwrap_or_default();
+        Self::row(&[
+            &code.code,
+            &optional(code.expires_in),
+            &optional(code.counter),
+        ])
+    }
+
+    fn audit(&self, report: &AuditReport) -> String {
+        report
+            .findings
+            .iter()
+            .flat_map(|entry| {
+                entry.findings.iter().map(|finding| {
+                    Self::row(&[
+                        &entry.entry,
+                        finding.kind(),
+                        &finding.to_string(),
+                        &entry.id.to_string(),
+                    ])
+                })
+            })
+            .collect()
+    }
+
+    fn password(&self, password: &str, strength: &Strength) -> String {
+        Self::row(&[
+            password,
+            strength.label,
+            &strength.entropy_bits.to_string(),
+            &strength.charset_bits.to_string(),
+        ])
+    }
+
+    fn message(&self, _message: &str) -> String {
+        String::new()
+    }
+
+    fn warning(&self, warning: &str) -> String {
+        Self::row(&["warning", warning])
+    }
+
+    fn error(&self, error: &AppError) -> String {
+        Self::row(&[error.code(), &error.to_string()])
+    }
+}
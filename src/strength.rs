@@ -0,0 +1,122 @@
+use serde::Serialize;
+
+use crate::generator::{LOWERCASE, NUMBERS, UPPERCASE};
+
+/// The lowest [`Strength::score`] that isn't considered weak
+pub const MIN_SCORE: u8 = 3;
+
+/// How hard a password would be to guess.
+///
+/// Combines zxcvbn's pattern matching, which catches dictionary words,
+/// keyboard patterns, dates & repeats, with the raw entropy of
+/// the characters used, which is all a truly random password has
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Strength {
+    /// From 0 (trivially guessable) to 4 (very hard to guess)
+    pub score: u8,
+    /// [`Strength::score`] as a word, e.g. `weak`
+    pub label: &'static str,
+    /// The estimated number of guesses needed to find the password, as bits.
+    /// zxcvbn doesn't consider which characters random-looking parts use,
+    /// so this is usually lower than [`Strength::charset_bits`] for generated passwords
+    pub entropy_bits: f64,
+    /// `length * log2(charset size)`, the entropy the password would have
+    /// if every character was picked at random from the classes it uses
+    pub charset_bits: f64,
+    /// What makes the password weak, if anything
+    pub warning: Option<String>,
+    /// How the password could be made stronger
+    pub suggestions: Vec<String>,
+}
+
+impl Strength {
+    /// Returns `true` if the password is too easy to guess to be relied on
+    pub fn is_weak(&self) -> bool {
+        self.score < MIN_SCORE
+    }
+}
+
+/// Estimate the strength of `password`.
+///
+/// `user_inputs` are other words the password shouldn't be based on,
+/// like the entry's name & username
+pub fn estimate(password: &str, user_inputs: &[&str]) -> Strength {
+    let user_inputs: Vec<&str> = user_inputs
+        .iter()
+        .copied()
+        .filter(|input| !input.is_empty())
+        .collect();
+    let estimate = zxcvbn::zxcvbn(password, &user_inputs);
+    let score = u8::from(estimate.score());
+    let charset_bits = charset_bits(password);
+    // Empty passwords need no guesses at all, giving -infinity
+    let pattern_bits = (estimate.guesses_log10() * 10f64.log2()).max(0.0);
+
+    let (warning, suggestions) = match estimate.feedback() {
+        Some(feedback) => (
+            feedback.warning().map(|warning| warning.to_string()),
+            feedback
+                .suggestions()
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        ),
+        None => (None, Vec::new()),
+    };
+
+    Strength {
+        score,
+        label: match score {
+            0 => "very weak",
+            1 => "weak",
+            2 => "fair",
+            3 => "strong",
+            _ => "very strong",
+        },
+        entropy_bits: round(pattern_bits),
+        charset_bits: round(charset_bits),
+        warning,
+        suggestions,
+    }
+}
+
+/// The entropy of `password` if each character was picked at random
+/// from every character in the classes it uses
+fn charset_bits(password: &str) -> f64 {
+    let mut classes = [false; 5];
+    for c in password.chars() {
+        let class = if LOWERCASE.contains(c) {
+            0
+        } else if UPPERCASE.contains(c) {
+            1
+        } else if NUMBERS.contains(c) {
+            2
+        } else if c.is_ascii() {
+            3
+        } else {
+            4
+        };
+        classes[class] = true;
+    }
+
+    // Everything that isn't a letter or number on a US keyboard,
+    // & a rough guess at the non-ASCII characters someone might use
+    let sizes = [26, 26, 10, 33, 100];
+    let charset: u32 = classes
+        .iter()
+        .zip(sizes)
+        .filter(|(used, _)| **used)
+        .map(|(_, size)| size)
+        .sum();
+
+    if charset == 0 {
+        return 0.0;
+    }
+
+    password.chars().count() as f64 * f64::from(charset).log2()
+}
+
+/// Round to one decimal place, since more would be false precision
+fn round(bits: f64) -> f64 {
+    (bits * 10.0).round() / 10.0
+}